@@ -0,0 +1,219 @@
+use std::collections::{HashMap, HashSet};
+
+use dsl::{Mesh, Link, Value, NodeDecls, CompiledMesh};
+
+/// Value with derivatives by several variables
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dual {
+    pub value: Value,
+    pub grad: Vec<Value>,
+}
+
+impl Dual {
+    /// value which does not depend on variables
+    pub fn constant(value: Value, vars: usize) -> Self {
+        Self { value, grad: vec![Value::from(0); vars] }
+    }
+
+    /// value of variable with index `var`
+    pub fn variable(value: Value, var: usize, vars: usize) -> Self {
+        let mut dual = Self::constant(value, vars);
+        dual.grad[var] = Value::from(1);
+        dual
+    }
+
+    pub fn neg(&self) -> Self {
+        Self { value: -self.value, grad: self.grad.iter().map(|d| -*d).collect() }
+    }
+
+    pub fn add(&self, other: &Dual) -> Self {
+        Self {
+            value: self.value + other.value,
+            grad: self.grad.iter().zip(&other.grad).map(|(a, b)| *a + *b).collect(),
+        }
+    }
+
+    pub fn mul(&self, other: &Dual) -> Self {
+        Self {
+            value: self.value * other.value,
+            grad: self.grad.iter().zip(&other.grad).map(|(a, b)| *a * other.value + self.value * *b).collect(),
+        }
+    }
+
+    pub fn inv(&self) -> Self {
+        let value = Value::from(1) / self.value;
+        let scale = -(value * value);
+        Self { value, grad: self.grad.iter().map(|d| *d * scale).collect() }
+    }
+}
+
+/// Derivatives of outputs by controls
+#[derive(Debug, Clone, PartialEq)]
+pub struct Jacobian {
+    pub outputs: Vec<Link>,
+    pub ctrls: Vec<String>,
+    /// Values of outputs at the operating point
+    pub values: Vec<Value>,
+    /// Row per output, column per control
+    pub matrix: Vec<Vec<Value>>,
+}
+
+impl Jacobian {
+    /// derivative of output by control
+    pub fn get<S: AsRef<str>>(&self, output: &Link, ctrl: S) -> Option<Value> {
+        let row = self.outputs.iter().position(|link| link == output)?;
+        let col = self.ctrls.iter().position(|name| name == ctrl.as_ref())?;
+        Some(self.matrix[row][col])
+    }
+}
+
+impl Mesh {
+    /// Evaluate derivatives of outputs by controls using forward-mode differentiation
+    ///
+    /// Controls take values from `point`, missing ones take values from mesh.
+    /// Every kind which affects outputs must declare derivatives.
+    pub fn jacobian<S: AsRef<str>>(&self, decls: &NodeDecls, point: &HashMap<String, Value>,
+                                   outputs: &[Link], ctrls: &[S]) -> Result<Jacobian, String> {
+        let ctrls: Vec<String> = ctrls.iter().map(|name| name.as_ref().to_string()).collect();
+
+        for name in &ctrls {
+            if self.get_ctrl(name).is_none() {
+                return Err(format!("Unknown control `{}`", name));
+            }
+        }
+
+        let vars = ctrls.len();
+        let mut duals: HashMap<Link, Dual> = self.ctrls.iter().map(|ctrl| {
+            let value = point.get(&ctrl.name).cloned().unwrap_or(ctrl.value);
+            let dual = match ctrls.iter().position(|name| *name == ctrl.name) {
+                Some(var) => Dual::variable(value, var, vars),
+                None => Dual::constant(value, vars),
+            };
+            (Link::ctrl(ctrl.name.clone()), dual)
+        }).collect();
+
+        let used: HashSet<_> = self.upstream_all(outputs).nodes.into_iter().collect();
+
+        for node in self.topo_order()? {
+            if !used.contains(&node.name) { continue; }
+
+            let decl = decls.get(&node.kind)
+                .ok_or_else(|| format!("Unsupported node kind `{}`", node.kind))?;
+
+            let args = decl.def.ins.iter().map(|kind| {
+                node.get_in(&kind.name)
+                    .and_then(|input| duals.get(&input.link).cloned())
+                    .ok_or_else(|| format!("Missing input `{}` of node `{}`", kind.name, node.name))
+            }).collect::<Result<Vec<_>, String>>()?;
+
+            let outs = decl.deriv(&args)
+                .ok_or_else(|| format!("Kind `{}` of node `{}` does not declare derivatives", node.kind, node.name))?;
+
+            for (kind, dual) in decl.def.outs.iter().zip(outs) {
+                duals.insert(Link::output(node.name.clone(), kind.name.clone()), dual);
+            }
+        }
+
+        let mut values = Vec::with_capacity(outputs.len());
+        let mut matrix = Vec::with_capacity(outputs.len());
+
+        for link in outputs {
+            let dual = duals.get(link).cloned().ok_or_else(|| format!("Unknown link `{}`", link))?;
+            values.push(dual.value);
+            matrix.push(dual.grad);
+        }
+
+        Ok(Jacobian { outputs: outputs.to_vec(), ctrls, values, matrix })
+    }
+}
+
+impl CompiledMesh {
+    /// Evaluate derivatives of outputs by controls at current values of controls
+    pub fn jacobian<S: AsRef<str>>(&self, decls: &NodeDecls, outputs: &[Link], ctrls: &[S]) -> Result<Jacobian, String> {
+        self.mesh().jacobian(decls, &self.ctrl_values(), outputs, ctrls)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{HashMap};
+    use dsl::{Mesh, Link, Value, NodeDecls, compile};
+    use dsl::testing::{opaque_ops};
+    use ops::{basic_ops};
+    use serde_json::{from_str};
+    use futures::{Stream};
+    use futures::stream::{empty};
+    use tokio::executor::current_thread::{block_on_all};
+
+    #[test]
+    fn test_jacobian() {
+        let ops = NodeDecls::new().with(basic_ops).with(opaque_ops);
+
+        let mesh: Mesh = from_str(r#"{
+  "nodes": [
+    { "name": "mul", "kind": "*", "ins": [
+      { "name": "a", "link": { "name": "a" } },
+      { "name": "b", "link": { "name": "b" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "add", "kind": "+", "ins": [
+      { "name": "a", "link": { "node": "mul", "out": "=" } },
+      { "name": "b", "link": { "name": "c" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "inv", "kind": "^-1", "ins": [
+      { "name": "a", "link": { "node": "add", "out": "=" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "neg", "kind": "-", "ins": [
+      { "name": "a", "link": { "name": "a" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "max", "kind": "max", "ins": [
+      { "name": "a", "link": { "name": "a" } },
+      { "name": "b", "link": { "name": "b" } }
+    ], "outs": [
+      { "name": "=" }
+    ] }
+  ],
+  "ctrls": [
+    { "name": "a", "value": "2" },
+    { "name": "b", "value": "3" },
+    { "name": "c", "value": "1" }
+  ]
+}"#).unwrap();
+
+        let (add, inv, neg) = (Link::output("add", "="), Link::output("inv", "="), Link::output("neg", "="));
+
+        let jacobian = mesh.jacobian(&ops, &HashMap::new(), &[add.clone(), inv.clone(), neg.clone()], &["a", "b", "c"]).unwrap();
+
+        let x = Value::from(1) / Value::from(7);
+        assert_eq!(jacobian.values, vec![7.into(), x, (-2).into()]);
+        assert_eq!(jacobian.matrix[0], vec![3.into(), 2.into(), 1.into()]);
+        assert_eq!(jacobian.get(&neg, "a"), Some((-1).into()));
+        assert_eq!(jacobian.get(&neg, "b"), Some(0.into()));
+        // d(1/x) = -dx/x^2, x = 7
+        assert_eq!(jacobian.get(&inv, "c"), Some(-(x * x)));
+
+        assert!(mesh.jacobian(&ops, &HashMap::new(), &[Link::output("max", "=")], &["a"]).is_err());
+        assert!(mesh.jacobian(&ops, &HashMap::new(), &[add.clone()], &["d"]).is_err());
+
+        // the same output may be asked twice
+        let jacobian = mesh.jacobian(&ops, &HashMap::new(), &[neg.clone(), neg.clone()], &["a"]).unwrap();
+        assert_eq!(jacobian.values, vec![(-2).into(), (-2).into()]);
+
+        let mut compiled = compile(&ops, &mesh, Box::new(empty())).unwrap();
+        compiled.set_ctrl("a", 4).unwrap();
+        let out = compiled.subscribe_all();
+        compiled.shutdown();
+        block_on_all(out.collect()).unwrap();
+
+        let jacobian = compiled.jacobian(&ops, &[add.clone()], &["b"]).unwrap();
+        assert_eq!(jacobian.values, vec![13.into()]);
+        assert_eq!(jacobian.get(&add, "b"), Some(4.into()));
+    }
+}
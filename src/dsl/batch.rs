@@ -0,0 +1,398 @@
+use std::fs::{File};
+use std::io::{Read, Write};
+use std::path::{Path};
+use std::collections::{HashMap, HashSet};
+
+use serde_json::{self, Value as Json, Map};
+
+use dsl::{Mesh, Link, Value, ValueStyle, NodeEval, NodeDecls, parse_value};
+
+/// Mesh prepared for direct evaluation of outputs
+///
+/// Values are kept in slots: controls first, then outputs of nodes.
+/// Only nodes which affect selected outputs are evaluated.
+pub struct Evaluator {
+    steps: Vec<EvalStep>,
+    ctrls: HashMap<String, usize>,
+    init: Vec<Value>,
+    outputs: Vec<usize>,
+}
+
+struct EvalStep {
+    eval: NodeEval,
+    ins: Vec<usize>,
+    outs: Vec<usize>,
+}
+
+impl Evaluator {
+    /// Every kind which affects outputs must declare evaluation
+    pub fn new(decls: &NodeDecls, mesh: &Mesh, outputs: &[Link]) -> Result<Self, String> {
+        let mut slots: HashMap<Link, usize> = HashMap::new();
+        let mut init = Vec::new();
+        let mut ctrls = HashMap::new();
+
+        for ctrl in &mesh.ctrls {
+            ctrls.insert(ctrl.name.clone(), init.len());
+            slots.insert(Link::ctrl(ctrl.name.clone()), init.len());
+            init.push(ctrl.value);
+        }
+
+        let used: HashSet<_> = mesh.upstream_all(outputs).nodes.into_iter().collect();
+        let mut steps = Vec::new();
+
+        for node in mesh.topo_order()? {
+            if !used.contains(&node.name) { continue; }
+
+            let decl = decls.get(&node.kind)
+                .ok_or_else(|| format!("Unsupported node kind `{}`", node.kind))?;
+
+            let eval = decl.eval_fn()
+                .ok_or_else(|| format!("Kind `{}` of node `{}` can not be evaluated", node.kind, node.name))?;
+
+            let ins = decl.def.ins.iter().map(|kind| {
+                node.get_in(&kind.name)
+                    .and_then(|input| slots.get(&input.link).cloned())
+                    .ok_or_else(|| format!("Missing input `{}` of node `{}`", kind.name, node.name))
+            }).collect::<Result<Vec<_>, String>>()?;
+
+            let outs = decl.def.outs.iter().map(|kind| {
+                slots.insert(Link::output(node.name.clone(), kind.name.clone()), init.len());
+                init.push(Value::from(0));
+                init.len() - 1
+            }).collect();
+
+            steps.push(EvalStep { eval, ins, outs });
+        }
+
+        let outputs = outputs.iter()
+            .map(|link| slots.get(link).cloned().ok_or_else(|| format!("Unknown link `{}`", link)))
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(Self { steps, ctrls, init, outputs })
+    }
+
+    /// Evaluate outputs, controls take values from `point`, missing ones take values from mesh
+    pub fn eval(&self, point: &HashMap<String, Value>) -> Vec<Value> {
+        let mut slots = self.init.clone();
+
+        for (name, value) in point {
+            if let Some(&slot) = self.ctrls.get(name) {
+                slots[slot] = *value;
+            }
+        }
+
+        self.run(slots)
+    }
+
+    fn run(&self, mut slots: Vec<Value>) -> Vec<Value> {
+        let mut args = Vec::new();
+
+        for step in &self.steps {
+            args.clear();
+            args.extend(step.ins.iter().map(|&slot| slots[slot]));
+
+            for (&slot, value) in step.outs.iter().zip((step.eval)(&args)) {
+                slots[slot] = value;
+            }
+        }
+
+        self.outputs.iter().map(|&slot| slots[slot]).collect()
+    }
+}
+
+impl Mesh {
+    /// Evaluate outputs directly, without building streams
+    ///
+    /// Controls take values from `point`, missing ones take values from mesh.
+    /// Every kind which affects outputs must declare evaluation.
+    pub fn evaluate(&self, decls: &NodeDecls, point: &HashMap<String, Value>, outputs: &[Link]) -> Result<Vec<Value>, String> {
+        Ok(Evaluator::new(decls, self, outputs)?.eval(point))
+    }
+}
+
+/// Table of values with named columns
+///
+/// Stored as CSV with header row or as JSON array of objects.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Table {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl Table {
+    pub fn new(columns: Vec<String>) -> Self {
+        Self { columns, rows: Vec::new() }
+    }
+
+    pub fn from_csv(text: &str) -> Result<Self, String> {
+        let mut records = parse_csv(text)?.into_iter();
+        let columns = records.next().ok_or_else(|| "Missing header row".to_string())?;
+        let mut table = Self::new(columns);
+
+        for (i, record) in records.enumerate() {
+            if record.len() != table.columns.len() {
+                return Err(format!("Row {} has {} fields but {} expected", i + 1, record.len(), table.columns.len()));
+            }
+            let row = record.iter().map(|field| parse_value(field)
+                                        .map_err(|err| format!("{} at row {}", err, i + 1)))
+                .collect::<Result<Vec<_>, String>>()?;
+            table.rows.push(row);
+        }
+
+        Ok(table)
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut text = String::new();
+        push_csv_record(&mut text, self.columns.iter().map(String::as_str));
+        for row in &self.rows {
+            let fields: Vec<_> = row.iter().map(Value::to_string).collect();
+            push_csv_record(&mut text, fields.iter().map(String::as_str));
+        }
+        text
+    }
+
+    /// Columns are ordered by first appearance, every row must have all of them
+    pub fn from_json(json: &Json) -> Result<Self, String> {
+        let objects = json.as_array().ok_or_else(|| "Expected array of rows".to_string())?;
+        let mut columns: Vec<String> = Vec::new();
+
+        for object in objects {
+            let object = object.as_object().ok_or_else(|| "Expected row object".to_string())?;
+            for name in object.keys() {
+                if !columns.contains(name) {
+                    columns.push(name.clone());
+                }
+            }
+        }
+
+        let mut table = Self::new(columns);
+
+        for (i, object) in objects.iter().enumerate() {
+            let row = table.columns.iter().map(|name| match object.get(name) {
+                Some(&Json::String(ref text)) => parse_value(text),
+                Some(&Json::Number(ref number)) => parse_value(&number.to_string()),
+                Some(_) => Err(format!("Invalid value of `{}`", name)),
+                None => Err(format!("Missing `{}`", name)),
+            }.map_err(|err| format!("{} at row {}", err, i + 1))).collect::<Result<Vec<_>, String>>()?;
+            table.rows.push(row);
+        }
+
+        Ok(table)
+    }
+
+    /// Array of objects keyed by column names with values in specified style
+    pub fn to_json(&self, style: ValueStyle) -> Json {
+        Json::Array(self.rows.iter().map(|row| {
+            let mut object = Map::new();
+            for (name, value) in self.columns.iter().zip(row) {
+                object.insert(name.clone(), ::dsl::value::serialize_as(value, style, serde_json::value::Serializer)
+                              .unwrap_or(Json::Null));
+            }
+            Json::Object(object)
+        }).collect())
+    }
+
+    /// Load table in format guessed by extension: `.csv` or `.json`
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let mut text = String::new();
+
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut text))
+            .map_err(|err| format!("Unable to read `{}`: {}", path.display(), err))?;
+
+        let table = match table_ext(path) {
+            Some("csv") => Self::from_csv(&text),
+            Some("json") => serde_json::from_str(&text).map_err(|err| err.to_string()).and_then(|json| Self::from_json(&json)),
+            _ => return Err(format!("Unknown table format of `{}`", path.display())),
+        };
+
+        table.map_err(|err| format!("Invalid table `{}`: {}", path.display(), err))
+    }
+
+    /// Save table in format guessed by extension: `.csv` or `.json`
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+
+        let text = match table_ext(path) {
+            Some("csv") => self.to_csv(),
+            Some("json") => serde_json::to_string_pretty(&self.to_json(ValueStyle::String)).map_err(|err| err.to_string())?,
+            _ => return Err(format!("Unknown table format of `{}`", path.display())),
+        };
+
+        File::create(path)
+            .and_then(|mut file| file.write_all(text.as_bytes()))
+            .map_err(|err| format!("Unable to write `{}`: {}", path.display(), err))
+    }
+}
+
+fn table_ext(path: &Path) -> Option<&'static str> {
+    match path.extension()?.to_str()?.to_lowercase().as_str() {
+        "csv" => Some("csv"),
+        "json" => Some("json"),
+        _ => None,
+    }
+}
+
+/// Split CSV into records, quoted fields may contain separators, quotes and line breaks
+fn parse_csv(text: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => if chars.peek() == Some(&'"') {
+                chars.next();
+                field.push('"');
+            } else {
+                quoted = false;
+            },
+            '"' if field.is_empty() => quoted = true,
+            c if quoted => field.push(c),
+            ',' => record.push(field.split_off(0)),
+            '\r' => (),
+            '\n' => {
+                record.push(field.split_off(0));
+                if record.len() > 1 || !record[0].is_empty() {
+                    records.push(record.split_off(0));
+                } else {
+                    record.clear();
+                }
+            },
+            c => field.push(c),
+        }
+    }
+
+    if quoted {
+        return Err("Unterminated quoted field".into());
+    }
+
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+fn push_csv_record<'a, I: Iterator<Item = &'a str>>(text: &mut String, fields: I) {
+    for (i, field) in fields.enumerate() {
+        if i > 0 { text.push(','); }
+        if field.contains(|c: char| c == ',' || c == '"' || c == '\n' || c == '\r') {
+            text.push('"');
+            text.push_str(&field.replace('"', "\"\""));
+            text.push('"');
+        } else {
+            text.push_str(field);
+        }
+    }
+    text.push('\n');
+}
+
+/// Evaluate outputs of mesh for each row of controls
+///
+/// Columns of `ctrls` name controls, the others keep values from mesh.
+/// Result has a column per output named by link and a row per row of `ctrls`.
+pub fn batch(decls: &NodeDecls, mesh: &Mesh, ctrls: &Table, outputs: &[Link]) -> Result<Table, String> {
+    let evaluator = Evaluator::new(decls, mesh, outputs)?;
+
+    let slots = ctrls.columns.iter().map(|name| match mesh.get_ctrl(name) {
+        None => Err(format!("Unknown control `{}`", name)),
+        Some(ctrl) if ctrl.is_constant() => Err(format!("Control `{}` is constant", name)),
+        _ => Ok(evaluator.ctrls[name]),
+    }).collect::<Result<Vec<_>, String>>()?;
+
+    let mut table = Table::new(outputs.iter().map(Link::to_string).collect());
+
+    for (i, row) in ctrls.rows.iter().enumerate() {
+        if row.len() != ctrls.columns.len() {
+            return Err(format!("Row {} has {} fields but {} expected", i + 1, row.len(), ctrls.columns.len()));
+        }
+        let mut values = evaluator.init.clone();
+        for (&slot, value) in slots.iter().zip(row) {
+            values[slot] = *value;
+        }
+        table.rows.push(evaluator.run(values));
+    }
+
+    Ok(table)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{HashMap};
+    use super::{Table, Evaluator, batch};
+    use dsl::{Mesh, Link, ValueStyle, NodeDecls};
+    use ops::{basic_ops};
+    use serde_json::{from_str};
+
+    #[test]
+    fn test_batch() {
+        let ops = NodeDecls::new().with(basic_ops);
+
+        let mesh: Mesh = from_str(r#"{
+  "nodes": [
+    { "name": "mul", "kind": "*", "ins": [
+      { "name": "a", "link": { "name": "a" } },
+      { "name": "b", "link": { "name": "b" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "add", "kind": "+", "ins": [
+      { "name": "a", "link": { "node": "mul", "out": "=" } },
+      { "name": "b", "link": { "name": "c" } }
+    ], "outs": [
+      { "name": "=" }
+    ] }
+  ],
+  "ctrls": [
+    { "name": "a", "value": "2" },
+    { "name": "b", "value": "3" },
+    { "name": "c", "value": "1", "const": true }
+  ]
+}"#).unwrap();
+
+        let outputs = [Link::output("add", "="), Link::output("mul", "=")];
+
+        let ctrls = Table::from_csv("a,b\n1,2\n3,\"4\"\r\n\n0.5,-2\n").unwrap();
+        let table = batch(&ops, &mesh, &ctrls, &outputs).unwrap();
+
+        assert_eq!(table.columns, vec!["add.=", "mul.="]);
+        assert_eq!(table.rows, vec![
+            vec![3.into(), 2.into()],
+            vec![13.into(), 12.into()],
+            vec![0.into(), (-1).into()],
+        ]);
+        assert_eq!(table.to_csv(), "add.=,mul.=\n3,2\n13,12\n0.0,-1.0\n");
+
+        let ctrls = Table::from_json(&json!([{ "b": 5 }, { "b": "0.1" }])).unwrap();
+        let table = batch(&ops, &mesh, &ctrls, &outputs[..1]).unwrap();
+
+        assert_eq!(table.to_json(ValueStyle::String), json!([{ "add.=": "11" }, { "add.=": "1.2" }]));
+        assert_eq!(table.to_json(ValueStyle::Number), json!([{ "add.=": 11 }, { "add.=": 1.2 }]));
+
+        assert!(batch(&ops, &mesh, &Table::from_csv("d\n1\n").unwrap(), &outputs).is_err());
+        assert!(batch(&ops, &mesh, &Table::from_csv("c\n1\n").unwrap(), &outputs).is_err());
+        assert!(batch(&ops, &mesh, &ctrls, &[Link::output("add", "-")]).is_err());
+
+        let mut short = Table::new(vec!["a".into(), "b".into()]);
+        short.rows.push(vec![1.into()]);
+        assert_eq!(batch(&ops, &mesh, &short, &outputs), Err("Row 1 has 1 fields but 2 expected".into()));
+
+        assert!(Table::from_csv("a,b\n1\n").is_err());
+        assert!(Table::from_csv("a\nx\n").is_err());
+        assert!(Table::from_json(&json!([{ "a": 1 }, { "b": 2 }])).is_err());
+
+        let mut columns = Table::new(vec!["x,\"y\"".into()]);
+        columns.rows.push(vec![1.into()]);
+        assert_eq!(Table::from_csv(&columns.to_csv()), Ok(columns));
+
+        let evaluator = Evaluator::new(&ops, &mesh, &outputs).unwrap();
+        assert_eq!(evaluator.eval(&HashMap::new()), vec![7.into(), 6.into()]);
+        assert_eq!(evaluator.eval(&vec![("a".into(), 0.into())].into_iter().collect()), vec![1.into(), 0.into()]);
+    }
+}
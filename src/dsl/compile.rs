@@ -1,33 +1,85 @@
 use std::rc::{Rc};
-use std::collections::{HashMap};
+use std::cell::{RefCell};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use futures::{Stream};
-use futures::stream::{empty, once};
-use future_pubsub::unsync::{into_cloneable};
+use futures::{Stream, Future, Poll, Async};
+use futures::stream::{once};
+use futures::unsync::{oneshot};
 
-use dsl::{Mesh, Node, Link, Value, ValueCell, NodeDecl, NodeDecls, Observable, Observables};
+use dsl::{Mesh, MeshIndex, Node, Link, Value, ValueCell, Signal, NodeDecl, NodeDecls, NodeState, Observable, Observables, SharedStream, Driver, Intake, Accepted, CompiledMesh, SUBSCRIPTION_CAPACITY, Snapshot, Journal, lint};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct InputControl {
     pub name: String,
+    #[serde(with = "::dsl::value")]
     pub value: Value,
+    /// Change ID assigned by mesh when control is accepted
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub id: u64,
+    /// Milliseconds since Unix epoch when control is accepted
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub time: u64,
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+impl InputControl {
+    pub fn new<S: Into<String>, V: Into<Value>>(name: S, value: V) -> Self {
+        Self { name: name.into(), value: value.into(), id: 0, time: 0 }
+    }
+
+    /// mark control as accepted with change ID
+    pub fn stamp(mut self, id: u64) -> Self {
+        self.id = id;
+        self.time = now_millis();
+        self
+    }
+
+    pub fn cause(&self) -> Cause {
+        Cause { name: self.name.clone(), id: self.id, time: self.time }
+    }
+}
+
+/// Milliseconds since Unix epoch
+pub fn now_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs() * 1000 + (time.subsec_nanos() / 1_000_000) as u64)
+        .unwrap_or(0)
 }
 
 pub type ControlStream = Box<Stream<Item = InputControl, Error = ()>>;
 
+/// Control which caused change
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Cause {
+    pub name: String,
+    pub id: u64,
+    pub time: u64,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct OutputChange {
     pub link: Rc<Link>,
     pub value: Value,
+    /// Control which caused change, none for initial values
+    pub cause: Option<Rc<Cause>>,
 }
 
 impl OutputChange {
     pub fn new<V: Into<Value>>(link: Link, value: V) -> Self {
-        Self { link: Rc::new(link), value: value.into() }
+        Self { link: Rc::new(link), value: value.into(), cause: None }
     }
 
     pub fn wrap<V: Into<Value>>(link: Rc<Link>, value: V) -> Self {
-        Self { link: link.clone(), value: value.into() }
+        Self { link: link.clone(), value: value.into(), cause: None }
+    }
+
+    pub fn with_cause(mut self, cause: Option<Rc<Cause>>) -> Self {
+        self.cause = cause;
+        self
     }
 }
 
@@ -35,8 +87,58 @@ pub type ChangesStream = Box<Stream<Item = OutputChange, Error = ()>>;
 
 pub type ValuesMap = HashMap<Rc<Link>, ValueCell>;
 
-pub fn compile(decls: &NodeDecls, mesh: &Mesh, ctrl_stream: ControlStream) -> Result<(ValuesMap, ChangesStream), String> {
-    let ctrl_stream = into_cloneable(ctrl_stream);
+/// Compile mesh into handle which lets set controls and subscribe to changes of outputs
+///
+/// Controls come both from `ctrl_stream` and from `CompiledMesh::set_ctrl`.
+pub fn compile(decls: &NodeDecls, mesh: &Mesh, ctrl_stream: ControlStream) -> Result<CompiledMesh, String> {
+    compile_with(decls, mesh, ctrl_stream, None, None)
+}
+
+/// Compile mesh resuming from runtime state saved by `CompiledMesh::save_state`
+///
+/// Controls take saved values unless they are constant, links get saved values
+/// until nodes produce new ones and stateful nodes restore their internal state.
+pub fn resume(decls: &NodeDecls, mesh: &Mesh, ctrl_stream: ControlStream, snapshot: &Snapshot) -> Result<CompiledMesh, String> {
+    compile_with(decls, mesh, ctrl_stream, Some(snapshot), None)
+}
+
+/// Compile mesh which appends accepted controls to journal
+///
+/// Controls which are already in journal are replayed first to reconstruct the state.
+pub fn compile_journaled(decls: &NodeDecls, mesh: &Mesh, ctrl_stream: ControlStream, journal: Journal) -> Result<CompiledMesh, String> {
+    compile_with(decls, mesh, ctrl_stream, None, Some(journal))
+}
+
+fn compile_with(decls: &NodeDecls, mesh: &Mesh, ctrl_stream: ControlStream,
+                snapshot: Option<&Snapshot>, journal: Option<Journal>) -> Result<CompiledMesh, String> {
+    let (stop_sender, stop_receiver) = oneshot::channel();
+
+    let mut intake = Intake::new(mesh);
+    if let Some(journal) = journal {
+        // journaled controls are accepted again, but not recorded twice
+        for entry in journal.entries()? {
+            if let Err(err) = intake.replay(entry.ctrl()) {
+                warn!("Skip journaled control: {}", err);
+            }
+        }
+        intake = intake.with_journal(journal);
+    }
+    let intake = Rc::new(RefCell::new(intake));
+
+    // streams are polled by driver in order of instantiation, it has to outlive them
+    let driver = Rc::new(Driver::new(SUBSCRIPTION_CAPACITY));
+
+    // external controls end on shutdown, accepted ones end after they are delivered
+    let ctrl_stream = SharedStream::new(Accepted::new(intake.clone(), Box::new(StopOn::new(ctrl_stream, stop_receiver))));
+    driver.add(&ctrl_stream);
+
+    // check for duplicates and unused elements
+    for lint in lint(mesh, Some(decls)) {
+        if lint.is_error() { return Err(lint.message); }
+        warn!("{}", lint);
+    }
+
+    let index = mesh.index();
     
     // validate nodes
     for ref node in &mesh.nodes {
@@ -46,75 +148,245 @@ pub fn compile(decls: &NodeDecls, mesh: &Mesh, ctrl_stream: ControlStream) -> Re
         let decl = if let Some(decl) = decls.get(&node.kind) { decl }
         else { return Err(format!("Unsupported node kind `{}`", node.kind)); };
 
-        validate_inputs(mesh, decl, node)?;
+        validate_inputs(&index, decl, node)?;
         validate_outputs(decl, node)?;
     }
 
+    validate_exports(mesh, &index)?;
+
+    // skip elements which do not affect exports
+    let used = if mesh.exports.is_empty() { None } else {
+        let deps = mesh.upstream_all(mesh.exports.iter().map(|export| &export.link));
+        Some((deps.ctrls.into_iter().collect::<HashSet<_>>(),
+              deps.nodes.into_iter().collect::<HashSet<_>>()))
+    };
+
+    let saved_ctrls: HashMap<_, _> = snapshot.iter()
+        .flat_map(|snapshot| snapshot.ctrls.iter().map(|ctrl| (ctrl.name.as_str(), ctrl.value)))
+        .collect();
+    let saved_nodes: HashMap<_, _> = snapshot.iter()
+        .flat_map(|snapshot| snapshot.nodes.iter().map(|node| (node.name.as_str(), &node.state)))
+        .collect();
+
     let mut observables: HashMap<Link, Observable> = HashMap::new();
 
     for ref ctrl in &mesh.ctrls {
+        if let Some((ref used_ctrls, _)) = used {
+            if !used_ctrls.contains(&ctrl.name) { continue; }
+        }
+
         let name = ctrl.name.clone();
-        let stream = once(Ok(ctrl.value))
-            .chain(ctrl_stream.clone()
-                   .filter(move |item| item.name == name)
-                   .map(|item| item.value))
-            .map(|v| { trace!("ctrl in {}", v); v })
+        // constant controls ignore changes
+        let constant = ctrl.is_constant();
+        let value = match saved_ctrls.get(ctrl.name.as_str()) {
+            Some(&value) if !constant => value,
+            _ => ctrl.value,
+        };
+        let stream = once(Ok(Signal::new(value)))
+            .chain(ctrl_stream.receive()
+                   .filter(move |item| !constant && item.name == name)
+                   .map(|item| Signal::new(item.value).with_cause(Some(Rc::new(item.cause())))))
+            .map(|signal| {
+                trace!("ctrl in {}", signal.value);
+                signal
+            })
             .map_err(|_| ());
         
-        observables.insert(Link::Ctrl { name: ctrl.name.clone() },
-                           Observable::from(stream));
+        let observable = Observable::from(stream);
+        observable.drive(&driver);
+        observables.insert(Link::Ctrl { name: ctrl.name.clone() }, observable);
     }
 
-    let mut nodes: Vec<_> = mesh.nodes.iter().collect();
-
-    loop {
-        let new_nodes: Vec<_> = nodes.iter().cloned().filter(|ref node| {
-            for ref input in &node.ins {
-                if !observables.contains_key(&input.link) {
-                    return true;
-                }
+    let nodes: Vec<_> = mesh.nodes.iter().filter(|node| match used {
+        Some((_, ref used_nodes)) => used_nodes.contains(&node.name),
+        None => true,
+    }).collect();
+
+    // instantiate nodes in topological order (Kahn's algorithm)
+    let positions: HashMap<_, _> = nodes.iter().enumerate()
+        .map(|(i, node)| (node.name.as_str(), i)).collect();
+    let mut pending = vec![0; nodes.len()];
+    let mut users = vec![Vec::new(); nodes.len()];
+
+    for (i, node) in nodes.iter().enumerate() {
+        for input in &node.ins {
+            if let Link::Output { node: ref link_node, .. } = input.link {
+                pending[i] += 1;
+                users[positions[link_node.as_str()]].push(i);
             }
+        }
+    }
+
+    let mut ready: VecDeque<_> = (0..nodes.len()).filter(|&i| pending[i] == 0).collect();
+
+    // instantiated pure computations by kind and inputs
+    let mut computations: HashMap<(&str, Vec<(&str, Link)>), &Node> = HashMap::new();
+    // outputs of reused nodes to original outputs
+    let mut canonical: HashMap<Link, Link> = HashMap::new();
+    // internal states of stateful nodes
+    let mut states: Vec<(String, Rc<NodeState>)> = Vec::new();
+
+    while let Some(i) = ready.pop_front() {
+        let node = nodes[i];
+        let decl = decls.get(&node.kind).unwrap();
+
+        if !reuse_node(&mut computations, &mut canonical, &mut observables, decl, node) {
             debug!("instantiate node `{}`", node.name);
             let mut ins = Observables::new();
             for ref input in &node.ins {
-                ins = ins.put(&input.name, observables.get(&input.link).unwrap().clone());
+                ins = ins.put(&input.name, observables[&input.link].clone());
             }
-            let decl = decls.get(&node.kind).unwrap();
             let mut outs = decl.imp(ins);
             for ref output in &node.outs {
-                observables.insert(Link::Output { node: node.name.clone(), out: output.name.clone() },
-                                   outs.get(&output.name));
+                let observable = outs.get(&output.name);
+                observable.drive(&driver);
+                observables.insert(Link::Output { node: node.name.clone(), out: output.name.clone() }, observable);
+            }
+            if let Some(state) = outs.take_state() {
+                if let Some(saved) = saved_nodes.get(node.name.as_str()) {
+                    state.restore((*saved).clone())
+                        .map_err(|err| format!("Unable to restore state of node `{}`: {}", node.name, err))?;
+                }
+                states.push((node.name.clone(), state));
             }
-            false
-        }).collect();
-        
-        if new_nodes.is_empty() { break; }
-        
-        if new_nodes.len() == nodes.len() {
-            return Err(format!("Unable to instantiate nodes {} due to cyclic dependencies",
-                               new_nodes.iter().fold(String::new(), |out, node| if out == "" {
-                                   format!("`{}`", node.name)
-                               } else {
-                                   format!("{}, `{}`", out, node.name)
-                               })));
         }
 
-        nodes = new_nodes;
+        for &j in &users[i] {
+            pending[j] -= 1;
+            if pending[j] == 0 {
+                ready.push_back(j);
+            }
+        }
     }
 
-    let mut values_map = ValuesMap::new();
-    let mut change_stream: ChangesStream = Box::new(empty());
-    
-    for (link, observable) in observables.iter() {
-        let (value, stream) = observable.clone().into();
-        let link = Rc::new(link.clone());
-        values_map.insert(link.clone(), value);
-        change_stream = Box::new(
-            change_stream.select(stream.map(move |value| OutputChange::wrap(link.clone(), value)))
-        );
+    if pending.iter().any(|&count| count > 0) {
+        return Err(format!("Unable to instantiate nodes {} due to cyclic dependencies",
+                           nodes.iter().zip(&pending)
+                           .filter(|&(_, &count)| count > 0)
+                           .map(|(node, _)| format!("`{}`", node.name))
+                           .collect::<Vec<_>>()
+                           .join(", ")));
+    }
+
+    let exports: Option<HashSet<_>> = if mesh.exports.is_empty() { None } else {
+        Some(mesh.exports.iter().map(|export| &export.link).collect())
+    };
+
+    if let Some(snapshot) = snapshot {
+        for saved in &snapshot.values {
+            if let Some(observable) = observables.get(&saved.link) {
+                *observable.value().borrow_mut() = Some(saved.value);
+            }
+        }
+    }
+
+    let cells: ValuesMap = observables.iter()
+        .map(|(link, observable)| (Rc::new(link.clone()), observable.value()))
+        .collect();
+
+    // streams of changes are taken on subscription
+    let outputs: HashMap<_, _> = observables.into_iter()
+        .filter(|&(ref link, _)| match exports {
+            Some(ref exports) => exports.contains(link),
+            None => true,
+        })
+        .map(|(link, observable)| (Rc::new(link), observable))
+        .collect();
+
+    Ok(CompiledMesh::new(mesh.clone(), outputs)
+       .with_driver(driver)
+       .with_intake(intake)
+       .with_stop(stop_sender)
+       .with_cells(cells)
+       .with_states(states))
+}
+
+/// Alias outputs of node to outputs of identical node instantiated before
+///
+/// Only pure nodes with the same kind and inputs may be reused.
+fn reuse_node<'a>(computations: &mut HashMap<(&'a str, Vec<(&'a str, Link)>), &'a Node>,
+                  canonical: &mut HashMap<Link, Link>,
+                  observables: &mut HashMap<Link, Observable>,
+                  decl: &NodeDecl, node: &'a Node) -> bool {
+    if !decl.def.pure { return false; }
+
+    let mut key_ins: Vec<_> = node.ins.iter().map(|input| {
+        (input.name.as_str(), canonical.get(&input.link).unwrap_or(&input.link).clone())
+    }).collect();
+    key_ins.sort_by(|a, b| a.0.cmp(b.0));
+    let key = (node.kind.as_str(), key_ins);
+
+    let orig = match computations.get(&key).cloned() {
+        Some(orig) => orig,
+        None => {
+            computations.insert(key, node);
+            return false;
+        },
+    };
+
+    if !node.outs.iter().all(|output| orig.get_out(&output.name).is_some()) {
+        return false;
+    }
+
+    debug!("reuse node `{}` as `{}`", orig.name, node.name);
+    for ref output in &node.outs {
+        let orig_link = Link::Output { node: orig.name.clone(), out: output.name.clone() };
+        let link = Link::Output { node: node.name.clone(), out: output.name.clone() };
+        let observable = observables[&orig_link].clone();
+        observables.insert(link.clone(), observable);
+        canonical.insert(link, orig_link);
+    }
+
+    true
+}
+
+/// Stream which ends when stop signal is sent or its sender is dropped
+struct StopOn<S: Stream> {
+    stream: S,
+    stop: oneshot::Receiver<()>,
+}
+
+impl<S: Stream> StopOn<S> {
+    fn new(stream: S, stop: oneshot::Receiver<()>) -> Self {
+        Self { stream, stop }
+    }
+}
+
+impl<S: Stream> Stream for StopOn<S> {
+    type Item = S::Item;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<S::Item>, S::Error> {
+        match self.stop.poll() {
+            Ok(Async::NotReady) => self.stream.poll(),
+            _ => Ok(Async::Ready(None)),
+        }
+    }
+}
+
+fn validate_exports(mesh: &Mesh, index: &MeshIndex) -> Result<(), String> {
+    for ref export in &mesh.exports {
+        if export.name == "" { return Err("Empty export name".into()); }
+
+        match &export.link {
+            &Link::Output { node: ref link_node_name, out: ref link_out } => {
+                if let Some(ref link_node) = index.get_node(link_node_name) {
+                    if link_node.get_out(link_out).is_none() {
+                        return Err(format!("Export `{}` linked to missing output `{}` of node `{}`", export.name, link_out, link_node_name));
+                    }
+                } else {
+                    return Err(format!("Export `{}` linked to missing node `{}`", export.name, link_node_name));
+                }
+            },
+            &Link::Ctrl { name: ref link_ctrl } => {
+                if index.get_ctrl(link_ctrl).is_none() {
+                    return Err(format!("Export `{}` linked to missing control `{}`", export.name, link_ctrl));
+                }
+            },
+        }
     }
 
-    Ok((values_map, change_stream))
+    Ok(())
 }
 
 fn validate_outputs(decl: &NodeDecl, node: &Node) -> Result<(), String> {
@@ -129,7 +401,7 @@ fn validate_outputs(decl: &NodeDecl, node: &Node) -> Result<(), String> {
     Ok(())
 }
 
-fn validate_inputs(mesh: &Mesh, decl: &NodeDecl, node: &Node) -> Result<(), String> {
+fn validate_inputs(index: &MeshIndex, decl: &NodeDecl, node: &Node) -> Result<(), String> {
     // check missing inputs
     for ref input_kind in &decl.def.ins {
         if node.get_in(&input_kind.name).is_none() {
@@ -146,7 +418,7 @@ fn validate_inputs(mesh: &Mesh, decl: &NodeDecl, node: &Node) -> Result<(), Stri
         
         match &input.link {
             &Link::Output { node: ref link_node_name, out: ref link_out } => {
-                if let Some(ref link_node) = mesh.get_node(link_node_name) {
+                if let Some(ref link_node) = index.get_node(link_node_name) {
                     if link_node.get_out(link_out).is_none() {
                         return Err(format!("Input `{}` of node `{}` linked to missing output `{}` of node `{}`", input.name, node.name, link_out, link_node_name));
                     }
@@ -155,7 +427,7 @@ fn validate_inputs(mesh: &Mesh, decl: &NodeDecl, node: &Node) -> Result<(), Stri
                 }
             },
             &Link::Ctrl { name: ref link_ctrl } => {
-                if mesh.get_ctrl(link_ctrl).is_none() {
+                if index.get_ctrl(link_ctrl).is_none() {
                     return Err(format!("Input `{}` of node `{}` linked to missing control `{}`", input.name, node.name, link_ctrl));
                 }
             },
@@ -167,11 +439,12 @@ fn validate_inputs(mesh: &Mesh, decl: &NodeDecl, node: &Node) -> Result<(), Stri
 
 #[cfg(test)]
 mod test {
-    use dsl::{Mesh, Link, NodeDecls, compile};
+    use std::rc::{Rc};
+    use dsl::{Mesh, Node, Input, Output, Ctrl, Export, Link, Value, NodeDecls, InputControl, compile, drain};
     use ops::{basic_ops};
     use serde_json::{from_str};
     use futures::{Stream, Future};
-    use futures::stream::{empty};
+    use futures::stream::{empty, iter_ok};
     use futures::future::{lazy};
     use tokio::executor::current_thread::{block_on_all, spawn};
 
@@ -207,20 +480,17 @@ mod test {
         
         assert!(res.is_ok());
 
-        let (_, out) = res.unwrap();
+        let mut compiled = res.unwrap();
+        let out = compiled.subscribe_all();
+        compiled.shutdown();
 
         block_on_all(lazy(|| {
             spawn(out.collect().map(|vals| {
-                println!("{:?}", vals);
-                assert_eq!(vals.iter()
-                           .filter(|out| *out.link == Link::output("mul", "="))
-                           .map(|out| out.value)
-                           .last(), Some(6.into()));
-                assert_eq!(vals.iter()
-                           .filter(|out| *out.link == Link::output("add", "="))
-                           .map(|out| out.value)
-                           .last(), Some(7.into()));
-                assert!(false);
+                let mut vals: Vec<_> = vals.iter().map(|out| (out.link.to_string(), out.value)).collect();
+                vals.sort_by(|a, b| a.0.cmp(&b.0));
+                let expected: Vec<(String, Value)> = vec![("$a".into(), 2.into()), ("$b".into(), 3.into()), ("$c".into(), 1.into()),
+                                                          ("add.=".into(), 7.into()), ("mul.=".into(), 6.into())];
+                assert_eq!(vals, expected);
             }));
 
             Ok::<_, ()>(())
@@ -260,4 +530,268 @@ mod test {
             assert_eq!(err.as_str(), "Unable to instantiate nodes `mul`, `add` due to cyclic dependencies");
         }
     }
+
+    #[test]
+    fn test_compile_err_duplicate_ctrl() {
+        let ops = NodeDecls::new().with(basic_ops);
+
+        let mesh: Mesh = from_str(r#"{
+  "nodes": [
+    { "name": "neg", "kind": "-", "ins": [
+      { "name": "a", "link": { "name": "a" } }
+    ], "outs": [
+      { "name": "=" }
+    ] }
+  ],
+  "ctrls": [
+    { "name": "a", "value": "1" },
+    { "name": "a", "value": "2" }
+  ]
+}"#).unwrap();
+
+        let res = compile(&ops, &mesh, Box::new(empty()));
+
+        assert!(res.is_err());
+
+        if let Err(ref err) = res {
+            assert_eq!(err.as_str(), "Duplicate control `a`");
+        }
+    }
+
+    #[test]
+    fn test_compile_causes() {
+        let ops = NodeDecls::new().with(basic_ops);
+
+        let mesh: Mesh = from_str(r#"{
+  "nodes": [
+    { "name": "neg", "kind": "-", "ins": [
+      { "name": "a", "link": { "name": "a" } }
+    ], "outs": [
+      { "name": "=" }
+    ] }
+  ],
+  "ctrls": [
+    { "name": "a", "value": "1" }
+  ]
+}"#).unwrap();
+
+        let ctrl = InputControl { id: 42, ..InputControl::new("a", 5) };
+        let mut compiled = compile(&ops, &mesh, Box::new(iter_ok(vec![ctrl]))).unwrap();
+        let mut out = compiled.subscribe(&["neg.="]).unwrap();
+
+        // external controls get their IDs when mesh receives them, even if they have some
+        let vals = drain(&mut out).unwrap();
+
+        assert_eq!(vals.first().map(|out| out.cause.is_none()), Some(true));
+        assert_eq!(vals.last().map(|out| (out.value, out.cause.as_ref().map(|cause| cause.id))),
+                   Some(((-5).into(), Some(1))));
+
+        let id = compiled.set_ctrl("a", 6).unwrap();
+        assert_eq!(id, 2);
+        assert_eq!(compiled.set_ctrl("a", 7), Ok(id + 1));
+        compiled.shutdown();
+
+        let vals = block_on_all(out.collect()).unwrap();
+
+        assert_eq!(vals.last().map(|out| out.value), Some((-7).into()));
+
+        let cause = vals.last().and_then(|out| out.cause.clone()).unwrap();
+        assert_eq!((cause.name.as_str(), cause.id), ("a", id + 1));
+        assert!(cause.time > 0);
+    }
+
+    #[test]
+    fn test_compile_branch_causes() {
+        let ops = NodeDecls::new().with(basic_ops);
+
+        let mesh: Mesh = from_str(r#"{
+  "nodes": [
+    { "name": "neg_a", "kind": "-", "ins": [
+      { "name": "a", "link": { "name": "a" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "neg_b", "kind": "-", "ins": [
+      { "name": "a", "link": { "name": "b" } }
+    ], "outs": [
+      { "name": "=" }
+    ] }
+  ],
+  "ctrls": [
+    { "name": "a", "value": "1" },
+    { "name": "b", "value": "1" }
+  ]
+}"#).unwrap();
+
+        let compiled = compile(&ops, &mesh, Box::new(empty())).unwrap();
+        let mut out = compiled.subscribe(&["neg_a.=", "neg_b.="]).unwrap();
+        drain(&mut out).unwrap();
+
+        // each change carries the control which caused it
+        assert_eq!(compiled.set_ctrl("a", 2), Ok(1));
+        assert_eq!(compiled.set_ctrl("b", 3), Ok(2));
+
+        let mut causes: Vec<_> = drain(&mut out).unwrap().into_iter()
+            .map(|out| (out.link.to_string(), out.value, out.cause.map(|cause| (cause.name.clone(), cause.id))))
+            .collect();
+        causes.sort_by(|a, b| a.0.cmp(&b.0));
+
+        assert_eq!(causes, vec![
+            ("neg_a.=".into(), (-2).into(), Some(("a".into(), 1))),
+            ("neg_b.=".into(), (-3).into(), Some(("b".into(), 2))),
+        ]);
+    }
+
+    #[test]
+    fn test_compile_exports() {
+        let ops = NodeDecls::new().with(basic_ops);
+
+        let mesh: Mesh = from_str(r#"{
+  "nodes": [
+    { "name": "mul", "kind": "*", "ins": [
+      { "name": "a", "link": { "name": "a" } },
+      { "name": "b", "link": { "name": "b" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "add", "kind": "+", "ins": [
+      { "name": "a", "link": { "node": "mul", "out": "=" } },
+      { "name": "b", "link": { "name": "c" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "neg", "kind": "-", "ins": [
+      { "name": "a", "link": { "name": "d" } }
+    ], "outs": [
+      { "name": "=" }
+    ] }
+  ],
+  "ctrls": [
+    { "name": "a", "value": "2" },
+    { "name": "b", "value": "3" },
+    { "name": "c", "value": "1" },
+    { "name": "d", "value": "5" }
+  ],
+  "exports": [
+    { "name": "result", "link": { "node": "add", "out": "=" } }
+  ]
+}"#).unwrap();
+
+        let mut compiled = compile(&ops, &mesh, Box::new(empty())).unwrap();
+        let values = compiled.values().clone();
+        let out = compiled.subscribe_all();
+        compiled.shutdown();
+        let result = mesh.get_export("result").unwrap().link.clone();
+
+        assert_eq!(values.keys().map(|link| (**link).clone()).collect::<Vec<_>>(),
+                   vec![result.clone()]);
+
+        block_on_all(lazy(|| {
+            spawn(out.collect().map(move |vals| {
+                assert!(vals.iter().all(|out| *out.link == result));
+                assert_eq!(vals.last().map(|out| out.value), Some(7.into()));
+            }));
+
+            Ok::<_, ()>(())
+        })).unwrap();
+    }
+
+    #[test]
+    fn test_compile_reuse_pure_nodes() {
+        let ops = NodeDecls::new().with(basic_ops);
+
+        let mesh: Mesh = from_str(r#"{
+  "nodes": [
+    { "name": "mul1", "kind": "*", "ins": [
+      { "name": "a", "link": { "name": "a" } },
+      { "name": "b", "link": { "name": "b" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "mul2", "kind": "*", "ins": [
+      { "name": "b", "link": { "name": "b" } },
+      { "name": "a", "link": { "name": "a" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "neg1", "kind": "-", "ins": [
+      { "name": "a", "link": { "node": "mul1", "out": "=" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "neg2", "kind": "-", "ins": [
+      { "name": "a", "link": { "node": "mul2", "out": "=" } }
+    ], "outs": [
+      { "name": "=" }
+    ] }
+  ],
+  "ctrls": [
+    { "name": "a", "value": "2" },
+    { "name": "b", "value": "3" }
+  ]
+}"#).unwrap();
+
+        let mut compiled = compile(&ops, &mesh, Box::new(empty())).unwrap();
+        let values = compiled.values().clone();
+        let out = compiled.subscribe_all();
+        compiled.shutdown();
+
+        assert!(Rc::ptr_eq(&values[&Link::output("mul1", "=")], &values[&Link::output("mul2", "=")]));
+        assert!(Rc::ptr_eq(&values[&Link::output("neg1", "=")], &values[&Link::output("neg2", "=")]));
+
+        block_on_all(lazy(|| {
+            spawn(out.collect().map(|vals| {
+                for name in &["neg1", "neg2"] {
+                    assert_eq!(vals.iter()
+                               .filter(|out| *out.link == Link::output(*name, "="))
+                               .map(|out| out.value)
+                               .last(), Some((-6).into()));
+                }
+            }));
+
+            Ok::<_, ()>(())
+        })).unwrap();
+    }
+
+    #[test]
+    fn test_compile_long_chain() {
+        const LENGTH: usize = 100_000;
+
+        fn neg_node(i: usize) -> Node {
+            Node {
+                kind: "-".into(),
+                name: format!("neg{}", i),
+                info: None,
+                ins: vec![Input {
+                    name: "a".into(),
+                    info: None,
+                    link: if i == 0 { Link::ctrl("x") } else { Link::output(format!("neg{}", i - 1), "=".into()) },
+                }],
+                outs: vec![Output { name: "=".into(), info: None }],
+            }
+        }
+
+        let mesh = Mesh {
+            nodes: (0..LENGTH).map(neg_node).collect(),
+            ctrls: vec![Ctrl::new("x", 1.into())],
+            exports: vec![Export { name: "result".into(), info: None, link: Link::output(format!("neg{}", LENGTH - 1), "=".into()) }],
+        };
+        let last = Link::output(format!("neg{}", LENGTH - 1), "=".into());
+
+        let ops = NodeDecls::new().with(basic_ops);
+        let compiled = compile(&ops, &mesh, Box::new(empty())).unwrap();
+        let mut out = compiled.subscribe_links(&[last.clone()]);
+
+        // even number of negations
+        assert_eq!(drain(&mut out).unwrap().last().map(|out| out.value), Some(1.into()));
+
+        compiled.set_ctrl("x", 2).unwrap();
+        assert_eq!(drain(&mut out).unwrap().last().map(|out| out.value), Some(2.into()));
+        assert_eq!(compiled.get(&last), Some(2.into()));
+        assert_eq!(compiled.values().len(), 1);
+
+        // dropped handle shuts mesh down
+        drop(compiled);
+        assert!(block_on_all(out.collect()).unwrap().is_empty());
+    }
 }
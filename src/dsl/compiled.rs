@@ -0,0 +1,516 @@
+use std::rc::{Rc};
+use std::sync::{Arc};
+use std::cell::{RefCell};
+use std::collections::{HashMap};
+
+use futures::{Stream, Poll, Async};
+use futures::stream::{FuturesUnordered, StreamFuture};
+use futures::executor::{self, Notify, NotifyHandle};
+use futures::unsync::{oneshot};
+
+use dsl::{Mesh, Link, LinkPattern, Value, InputControl, OutputChange, ChangesStream, ValuesMap,
+          Observable, Driver, Intake, NodeState, Snapshot, LinkValue, NodeSnapshot};
+
+/// Default number of changes of each link which subscription keeps until they are polled
+pub const SUBSCRIPTION_CAPACITY: usize = 1024;
+
+/// Merged stream of many streams
+///
+/// Unlike chain of `select`s it polls only streams which are ready,
+/// so the cost of delivering an item does not depend on the number of streams.
+struct FanIn<S: Stream> {
+    streams: FuturesUnordered<StreamFuture<S>>,
+}
+
+impl<S: Stream> FanIn<S> {
+    fn new(streams: Vec<S>) -> Self {
+        Self { streams: streams.into_iter().map(Stream::into_future).collect() }
+    }
+}
+
+impl<S: Stream> Stream for FanIn<S> {
+    type Item = S::Item;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<S::Item>, S::Error> {
+        loop {
+            match self.streams.poll() {
+                Ok(Async::Ready(Some((Some(item), rest)))) => {
+                    self.streams.push(rest.into_future());
+                    return Ok(Async::Ready(Some(item)));
+                },
+                // stream ended
+                Ok(Async::Ready(Some((None, _)))) => continue,
+                Ok(Async::Ready(None)) => return Ok(Async::Ready(None)),
+                Ok(Async::NotReady) => return Ok(Async::NotReady),
+                Err((err, rest)) => {
+                    self.streams.push(rest.into_future());
+                    return Err(err);
+                },
+            }
+        }
+    }
+}
+
+
+struct Ignore;
+
+impl Notify for Ignore {
+    fn notify(&self, _id: usize) {}
+}
+
+/// Take items which stream yields without waiting
+///
+/// Nodes react to controls synchronously, so draining subscription after `set_ctrl`
+/// yields all changes caused by control. It should not be used while some task polls the same mesh.
+pub fn drain<S: Stream>(stream: &mut S) -> Result<Vec<S::Item>, S::Error> {
+    let notify = NotifyHandle::from(Arc::new(Ignore));
+    let mut stream = executor::spawn(stream);
+    let mut items = Vec::new();
+
+    while let Async::Ready(Some(item)) = stream.poll_stream_notify(&notify, 0)? {
+        items.push(item);
+    }
+
+    Ok(items)
+}
+
+/// Stream of changes of subscribed links
+///
+/// Polling it runs the mesh until changes are ready.
+pub struct Subscription {
+    changes: FanIn<ChangesStream>,
+    driver: Rc<Driver>,
+}
+
+impl Stream for Subscription {
+    type Item = OutputChange;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Option<OutputChange>, ()> {
+        if let Async::Ready(change) = self.changes.poll()? {
+            return Ok(Async::Ready(change));
+        }
+        self.driver.run();
+        if let Async::Ready(change) = self.changes.poll()? {
+            return Ok(Async::Ready(change));
+        }
+        self.driver.wait();
+        Ok(Async::NotReady)
+    }
+}
+
+/// Instantiated mesh
+///
+/// Holds current values of outputs and lets clients set controls and subscribe to changes of chosen links.
+/// Mesh runs when controls are set by `set_ctrl` and while subscriptions are polled,
+/// so values of links are up to date right after `set_ctrl` even without subscriptions.
+/// Subscriptions get changes made after they are created. Mesh stops when handle is shut down or dropped.
+pub struct CompiledMesh {
+    mesh: Mesh,
+    values: ValuesMap,
+    outputs: HashMap<Rc<Link>, Observable>,
+    capacity: usize,
+    intake: Option<Rc<RefCell<Intake>>>,
+    stop: Option<oneshot::Sender<()>>,
+    cells: ValuesMap,
+    states: Vec<(String, Rc<NodeState>)>,
+    /// Dropped last, since it releases streams of nodes in order
+    driver: Rc<Driver>,
+}
+
+impl CompiledMesh {
+    pub fn new(mesh: Mesh, outputs: HashMap<Rc<Link>, Observable>) -> Self {
+        let values = outputs.iter().map(|(link, output)| (link.clone(), output.value())).collect();
+        Self {
+            mesh, values, outputs,
+            capacity: SUBSCRIPTION_CAPACITY,
+            intake: None, stop: None, cells: ValuesMap::new(), states: Vec::new(),
+            driver: Rc::new(Driver::new(SUBSCRIPTION_CAPACITY)),
+        }
+    }
+
+    /// number of changes of each link which subscription keeps until they are polled, older ones are dropped
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// scheduler which polls streams of nodes
+    pub fn with_driver(mut self, driver: Rc<Driver>) -> Self {
+        self.driver = driver;
+        self
+    }
+
+    /// entry which controls are accepted through by `set_ctrl`
+    pub fn with_intake(mut self, intake: Rc<RefCell<Intake>>) -> Self {
+        self.intake = Some(intake);
+        self
+    }
+
+    /// channel which terminates controls on shutdown
+    pub fn with_stop(mut self, stop: oneshot::Sender<()>) -> Self {
+        self.stop = Some(stop);
+        self
+    }
+
+    /// values of all links including ones which are not exported
+    pub fn with_cells(mut self, cells: ValuesMap) -> Self {
+        self.cells = cells;
+        self
+    }
+
+    /// internal states of stateful nodes
+    pub fn with_states(mut self, states: Vec<(String, Rc<NodeState>)>) -> Self {
+        self.states = states;
+        self
+    }
+
+    /// source mesh
+    pub fn mesh(&self) -> &Mesh {
+        &self.mesh
+    }
+
+    /// current values of outputs
+    pub fn values(&self) -> &ValuesMap {
+        &self.values
+    }
+
+    /// current value of link, including ones which are not exported
+    pub fn get(&self, link: &Link) -> Option<Value> {
+        self.cells.get(link).or_else(|| self.values.get(link)).and_then(|value| *value.borrow())
+    }
+
+    /// current values of all outputs which are already known
+    pub fn snapshot(&self) -> HashMap<Link, Value> {
+        self.values.iter()
+            .filter_map(|(link, value)| value.borrow().map(|value| ((**link).clone(), value)))
+            .collect()
+    }
+
+    /// current values of controls
+    pub fn ctrl_values(&self) -> HashMap<String, Value> {
+        self.mesh.ctrls.iter()
+            .filter_map(|ctrl| self.get(&Link::ctrl(ctrl.name.clone())).map(|value| (ctrl.name.clone(), value)))
+            .collect()
+    }
+
+    /// runtime state which mesh may be resumed from
+    pub fn save_state(&self) -> Result<Snapshot, String> {
+        let ctrls = self.mesh.ctrls.iter().map(|ctrl| {
+            let link = Link::ctrl(ctrl.name.clone());
+            let value = self.cells.get(&link).and_then(|value| *value.borrow()).unwrap_or(ctrl.value);
+            InputControl::new(ctrl.name.clone(), value)
+        }).collect();
+
+        let mut values: Vec<_> = self.cells.iter()
+            .filter_map(|(link, value)| value.borrow().map(|value| LinkValue { link: (**link).clone(), value }))
+            .collect();
+        values.sort_by_key(|value| value.link.to_string());
+
+        let nodes = self.states.iter()
+            .map(|&(ref name, ref state)| state.save()
+                 .map(|state| NodeSnapshot { name: name.clone(), state })
+                 .map_err(|err| format!("Unable to save state of node `{}`: {}", name, err)))
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(Snapshot { ctrls, values, nodes })
+    }
+
+    /// change value of control
+    ///
+    /// Returns change ID which is carried by `OutputChange`s caused by control.
+    /// Mesh runs until the change is applied, so values of links reflect it on return.
+    pub fn set_ctrl<S: AsRef<str>, V: Into<Value>>(&self, name: S, value: V) -> Result<u64, String> {
+        let id = match self.intake {
+            Some(ref intake) => intake.borrow_mut().push(InputControl::new(name.as_ref(), value))?,
+            None => return Err("Mesh is shut down".into()),
+        };
+        self.driver.run();
+        Ok(id)
+    }
+
+    /// number of controls and values which wait in queues of mesh
+    pub fn pending(&self) -> usize {
+        self.intake.as_ref().map(|intake| intake.borrow().queued()).unwrap_or(0) + self.driver.queued()
+    }
+
+    /// call function for each control accepted from now on, both from control stream and `set_ctrl`
+    pub fn observe_ctrls<F: Fn(&InputControl) + 'static>(&self, observer: F) {
+        if let Some(ref intake) = self.intake {
+            intake.borrow_mut().observe(observer);
+        }
+    }
+
+    /// rewrite journal of running mesh keeping only the latest entry of each control
+    pub fn compact_journal(&self) -> Result<(), String> {
+        match self.intake {
+            Some(ref intake) => intake.borrow_mut().compact_journal(),
+            None => Err("Mesh is not journaled".into()),
+        }
+    }
+
+    /// stop accepting controls, so streams of nodes end once pending controls are applied
+    pub fn shutdown(&mut self) {
+        if let Some(ref intake) = self.intake {
+            intake.borrow_mut().close();
+        }
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        self.driver.run();
+    }
+
+    pub fn is_shut_down(&self) -> bool {
+        self.intake.as_ref().map(|intake| intake.borrow().is_closed()).unwrap_or(true)
+    }
+
+    /// outputs available for subscription
+    pub fn links(&self) -> Vec<Rc<Link>> {
+        self.values.keys().cloned().collect()
+    }
+
+    /// subscribe to changes of links which match any of patterns
+    pub fn subscribe<S: AsRef<str>>(&self, patterns: &[S]) -> Result<Subscription, String> {
+        let patterns = patterns.iter().map(LinkPattern::new).collect::<Result<Vec<_>, _>>()?;
+        Ok(self.subscribe_matching(|link| patterns.iter().any(|pattern| pattern.matches(link))))
+    }
+
+    /// subscribe to changes of links
+    pub fn subscribe_links(&self, links: &[Link]) -> Subscription {
+        self.subscribe_matching(|link| links.contains(link))
+    }
+
+    /// subscribe to changes of all links
+    pub fn subscribe_all(&self) -> Subscription {
+        self.subscribe_matching(|_| true)
+    }
+
+    fn subscribe_matching<F: Fn(&Link) -> bool>(&self, filter: F) -> Subscription {
+        let streams = self.outputs.iter()
+            .filter(|&(link, _)| filter(link))
+            .map(|(link, output)| {
+                let link = link.clone();
+                let stream: ChangesStream = Box::new(output.latest(self.capacity)
+                    .map(move |signal| OutputChange::wrap(link.clone(), signal.value).with_cause(signal.cause)));
+                stream
+            })
+            .collect();
+
+        Subscription { changes: FanIn::new(streams), driver: self.driver.clone() }
+    }
+}
+
+impl Drop for CompiledMesh {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dsl::{Mesh, Link, Signal, NodeKind, InputKind, OutputKind, Observables, NodeDecl, NodeDecls,
+              SUBSCRIPTION_CAPACITY, compile, drain};
+    use ops::{basic_ops};
+    use serde_json::{from_str};
+    use futures::{Stream, Future, Poll, Async};
+    use futures::stream::{empty, poll_fn};
+    use futures::future::{lazy};
+    use tokio::executor::current_thread::{block_on_all, spawn};
+
+    #[test]
+    fn test_subscribe() {
+        let ops = NodeDecls::new().with(basic_ops);
+
+        let mesh: Mesh = from_str(r#"{
+  "nodes": [
+    { "name": "mul", "kind": "*", "ins": [
+      { "name": "a", "link": { "name": "a" } },
+      { "name": "b", "link": { "name": "b" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "add", "kind": "+", "ins": [
+      { "name": "a", "link": { "node": "mul", "out": "=" } },
+      { "name": "b", "link": { "name": "c" } }
+    ], "outs": [
+      { "name": "=" }
+    ] }
+  ],
+  "ctrls": [
+    { "name": "a", "value": "2" },
+    { "name": "b", "value": "3" },
+    { "name": "c", "value": "1" }
+  ]
+}"#).unwrap();
+
+        let mut compiled = compile(&ops, &mesh, Box::new(empty())).unwrap();
+
+        let adds = compiled.subscribe(&["add.*"]).unwrap();
+        let ctrls = compiled.subscribe(&["$a", "$c"]).unwrap();
+
+        assert!(compiled.subscribe(&[""]).is_err());
+
+        compiled.shutdown();
+
+        block_on_all(lazy(|| {
+            spawn(adds.collect().map(|vals| {
+                assert!(vals.iter().all(|out| *out.link == Link::output("add", "=")));
+                assert_eq!(vals.last().map(|out| out.value), Some(7.into()));
+            }));
+
+            spawn(ctrls.collect().map(|vals| {
+                assert!(vals.iter().all(|out| *out.link == Link::ctrl("a") || *out.link == Link::ctrl("c")));
+            }));
+
+            Ok::<_, ()>(())
+        })).unwrap();
+
+        assert_eq!(*compiled.values()[&Link::output("mul", "=")].borrow(), Some(6.into()));
+    }
+
+    #[test]
+    fn test_set_ctrl() {
+        let ops = NodeDecls::new().with(basic_ops);
+
+        let mesh: Mesh = from_str(r#"{
+  "nodes": [
+    { "name": "add", "kind": "+", "ins": [
+      { "name": "a", "link": { "name": "a" } },
+      { "name": "b", "link": { "name": "b" } }
+    ], "outs": [
+      { "name": "=" }
+    ] }
+  ],
+  "ctrls": [
+    { "name": "a", "value": "2" },
+    { "name": "b", "value": "3", "const": true }
+  ]
+}"#).unwrap();
+
+        let mut compiled = compile(&ops, &mesh, Box::new(empty())).unwrap();
+        let add = Link::output("add", "=");
+        let out = compiled.subscribe_links(&[add.clone()]);
+
+        assert_eq!(compiled.mesh(), &mesh);
+        assert!(compiled.set_ctrl("c", 1).is_err());
+        assert!(compiled.set_ctrl("b", 1).is_err());
+
+        compiled.set_ctrl("a", 5).unwrap();
+
+        // values are updated without polling subscriptions
+        assert_eq!(compiled.get(&add), Some(8.into()));
+        assert_eq!(compiled.save_state().unwrap().get_value(&add), Some(8.into()));
+        assert_eq!(compiled.ctrl_values().get("a"), Some(&5.into()));
+
+        // later subscriptions get only later changes
+        let late = compiled.subscribe_links(&[add.clone()]);
+        compiled.shutdown();
+
+        assert!(block_on_all(late.collect()).unwrap().is_empty());
+
+        assert!(compiled.is_shut_down());
+        assert_eq!(compiled.set_ctrl("a", 1), Err("Mesh is shut down".into()));
+
+        let vals = block_on_all(out.collect()).unwrap();
+
+        assert_eq!(vals.last().map(|out| out.value), Some(8.into()));
+        assert_eq!(compiled.get(&add), Some(8.into()));
+        assert_eq!(compiled.snapshot().get(&Link::ctrl("a")), Some(&5.into()));
+    }
+
+    #[test]
+    fn test_subscription_capacity() {
+        let ops = NodeDecls::new().with(basic_ops);
+
+        let mesh: Mesh = from_str(r#"{
+  "nodes": [
+    { "name": "add", "kind": "+", "ins": [
+      { "name": "a", "link": { "name": "a" } },
+      { "name": "b", "link": { "name": "b" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "neg", "kind": "-", "ins": [
+      { "name": "a", "link": { "name": "b" } }
+    ], "outs": [
+      { "name": "=" }
+    ] }
+  ],
+  "ctrls": [
+    { "name": "a", "value": "2" },
+    { "name": "b", "value": "3" }
+  ]
+}"#).unwrap();
+
+        let mut compiled = compile(&ops, &mesh, Box::new(empty())).unwrap().with_capacity(1);
+        let add = Link::output("add", "=");
+
+        let mut all = compiled.subscribe_links(&[add.clone()]);
+        let latest = compiled.subscribe_links(&[add.clone()]);
+
+        let mut vals = drain(&mut all).unwrap();
+        for value in 1..6 {
+            compiled.set_ctrl("a", value).unwrap();
+            vals.extend(drain(&mut all).unwrap());
+        }
+        compiled.shutdown();
+
+        assert_eq!(vals.iter().map(|out| out.value).collect::<Vec<_>>(), vec![5.into(), 4.into(), 5.into(), 6.into(), 7.into(), 8.into()]);
+
+        // slow subscriber gets only the latest change
+        let vals = block_on_all(latest.collect()).unwrap();
+        assert_eq!(vals.iter().map(|out| out.value).collect::<Vec<_>>(), vec![8.into()]);
+
+        // links nobody subscribed to are computed as well
+        assert_eq!(compiled.get(&Link::output("neg", "=")), Some((-3).into()));
+    }
+
+    #[test]
+    fn test_unsubscribed_queues() {
+        // node which holds its input but never polls it
+        fn idle_impl(mut ins: Observables) -> Observables {
+            let input = ins.get("a").stream();
+            Observables::new().put("=", poll_fn(move || -> Poll<Option<Signal>, ()> {
+                let _ = &input;
+                Ok(Async::NotReady)
+            }))
+        }
+
+        let ops = NodeDecls::new().with(basic_ops)
+            .add(NodeDecl::new(
+                NodeKind::new("idle")
+                    .with_in(InputKind::new("a"))
+                    .with_out(OutputKind::new("=")),
+                idle_impl));
+
+        let mesh: Mesh = from_str(r#"{
+  "nodes": [
+    { "name": "neg", "kind": "-", "ins": [
+      { "name": "a", "link": { "name": "a" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "idle", "kind": "idle", "ins": [
+      { "name": "a", "link": { "name": "a" } }
+    ], "outs": [
+      { "name": "=" }
+    ] }
+  ],
+  "ctrls": [
+    { "name": "a", "value": "0" }
+  ]
+}"#).unwrap();
+
+        let compiled = compile(&ops, &mesh, Box::new(empty())).unwrap();
+
+        for value in 1..10_001 {
+            compiled.set_ctrl("a", value).unwrap();
+        }
+
+        // changes nobody takes are dropped, so queues stay bounded
+        assert!(compiled.pending() <= SUBSCRIPTION_CAPACITY, "{} values are queued", compiled.pending());
+        assert_eq!(compiled.get(&Link::output("neg", "=")), Some((-10_000).into()));
+        assert_eq!(compiled.get(&Link::output("idle", "=")), None);
+    }
+}
@@ -15,6 +15,11 @@ pub struct NodeKind {
     #[serde(default)]
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub outs: Vec<OutputKind>,
+
+    /// Outputs depend only on current values of inputs
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub pure: bool,
 }
 
 impl NodeKind {
@@ -24,6 +29,7 @@ impl NodeKind {
             info: None,
             ins: Vec::new(),
             outs: Vec::new(),
+            pure: false,
         }
     }
 
@@ -49,6 +55,11 @@ impl NodeKind {
         self.outs.push(output);
         self
     }
+
+    pub fn with_pure(mut self) -> Self {
+        self.pure = true;
+        self
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -97,7 +108,7 @@ impl OutputKind {
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Node {
     pub kind: String,
     pub name: String,
@@ -125,7 +136,7 @@ impl Node {
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Input {
     pub name: String,
 
@@ -138,7 +149,7 @@ pub struct Input {
     pub link: Link,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Output {
     pub name: String,
 
@@ -174,20 +185,67 @@ impl Link {
 
 pub type Value = d128;
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Ctrl {
     pub name: String,
 
+    #[serde(with = "::dsl::value")]
     pub value: Value,
+
+    /// Control never changes, so it may be folded
+    #[serde(rename = "const")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    constant: bool,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+impl Ctrl {
+    pub fn new<S: AsRef<str>>(name: S, value: Value) -> Self {
+        Self {
+            name: name.as_ref().into(),
+            value,
+            constant: false,
+        }
+    }
+
+    pub fn with_constant(mut self) -> Self {
+        self.constant = true;
+        self
+    }
+
+    /// Control never changes, so it may be folded
+    pub fn is_constant(&self) -> bool {
+        self.constant
+    }
+}
+
+/// Named output of mesh
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Export {
+    pub name: String,
+
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub info: Option<String>,
+
+    /// Exported node output or control
+    pub link: Link,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Mesh {
     /// nodes
     pub nodes: Vec<Node>,
 
     /// controls
     pub ctrls: Vec<Ctrl>,
+
+    /// exported outputs
+    ///
+    /// When empty, all links are treated as outputs of mesh.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub exports: Vec<Export>,
 }
 
 impl Mesh {
@@ -198,6 +256,22 @@ impl Mesh {
     pub fn get_ctrl<S: AsRef<str>>(&self, name: S) -> Option<&Ctrl> {
         self.ctrls.iter().find(|c| c.name == name.as_ref())
     }
+
+    pub fn get_export<S: AsRef<str>>(&self, name: S) -> Option<&Export> {
+        self.exports.iter().find(|e| e.name == name.as_ref())
+    }
+
+    /// Resolve name of export or link in textual form like `node.out` or `$ctrl`
+    pub fn resolve_link<S: AsRef<str>>(&self, link: S) -> Result<Link, String> {
+        if let Some(export) = self.get_export(&link) {
+            return Ok(export.link.clone());
+        }
+        link.as_ref().parse()
+    }
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
 }
 
 #[cfg(test)]
@@ -0,0 +1,210 @@
+use std::fmt;
+use std::collections::{HashSet, VecDeque};
+
+use serde_json::{self, Value as Json};
+
+use dsl::{Link, Value, CompiledMesh};
+
+/// Input of node with its current value
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Operand {
+    pub name: String,
+    pub link: Link,
+    #[serde(with = "::dsl::value::opt")]
+    pub value: Option<Value>,
+}
+
+/// Computation of output by node
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Derivation {
+    pub link: Link,
+    pub node: String,
+    pub kind: String,
+    #[serde(with = "::dsl::value::opt")]
+    pub value: Option<Value>,
+    pub ins: Vec<Operand>,
+}
+
+/// Derivation trace of link value
+///
+/// Steps go upstream starting from the explained link, each computation is listed once.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Explanation {
+    pub link: Link,
+    #[serde(with = "::dsl::value::opt")]
+    pub value: Option<Value>,
+    pub steps: Vec<Derivation>,
+}
+
+impl Explanation {
+    pub fn to_json(&self) -> Json {
+        serde_json::to_value(self).unwrap_or(Json::Null)
+    }
+}
+
+impl CompiledMesh {
+    /// Explain current value of link by computations which lead to it
+    pub fn explain(&self, link: &Link) -> Result<Explanation, String> {
+        let mesh = self.mesh();
+        let index = mesh.index();
+
+        match *link {
+            Link::Output { ref node, ref out } => {
+                if index.get_node(node).and_then(|node| node.get_out(out)).is_none() {
+                    return Err(format!("Unknown link `{}`", link));
+                }
+            },
+            Link::Ctrl { ref name } => {
+                if index.get_ctrl(name).is_none() {
+                    return Err(format!("Unknown link `{}`", link));
+                }
+            },
+        }
+
+        let mut steps = Vec::new();
+        let mut queue = VecDeque::new();
+        let mut visited = HashSet::new();
+
+        queue.push_back(link.clone());
+        visited.insert(link.clone());
+
+        while let Some(link) = queue.pop_front() {
+            let node = match link {
+                Link::Output { ref node, .. } => index.get_node(node),
+                Link::Ctrl { .. } => None,
+            };
+            let node = if let Some(node) = node { node } else { continue };
+
+            let ins = node.ins.iter().map(|input| Operand {
+                name: input.name.clone(),
+                link: input.link.clone(),
+                value: self.get(&input.link),
+            }).collect();
+
+            for input in &node.ins {
+                if visited.insert(input.link.clone()) {
+                    queue.push_back(input.link.clone());
+                }
+            }
+
+            steps.push(Derivation {
+                value: self.get(&link),
+                link,
+                node: node.name.clone(),
+                kind: node.kind.clone(),
+                ins,
+            });
+        }
+
+        Ok(Explanation { link: link.clone(), value: self.get(link), steps })
+    }
+}
+
+/// Renders trace like `add.= = mul.= (6) + c (1); mul.= = a (2) * b (3)`
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.steps.is_empty() {
+            return write!(f, "{}", Term(&self.link, self.value));
+        }
+
+        for (i, step) in self.steps.iter().enumerate() {
+            if i > 0 { f.write_str("; ")?; }
+            write!(f, "{}", step)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Derivation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let operand = |name: &str| self.ins.iter().find(|input| input.name == name)
+            .map(|input| Term(&input.link, input.value));
+
+        write!(f, "{} = ", self.link)?;
+
+        match (self.kind.as_str(), operand("a"), operand("b")) {
+            ("+", Some(a), Some(b)) if self.ins.len() == 2 => write!(f, "{} + {}", a, b),
+            ("*", Some(a), Some(b)) if self.ins.len() == 2 => write!(f, "{} * {}", a, b),
+            ("-", Some(a), None) if self.ins.len() == 1 => write!(f, "-{}", a),
+            ("^-1", Some(a), None) if self.ins.len() == 1 => write!(f, "{}^-1", a),
+            _ => {
+                write!(f, "{}(", self.kind)?;
+                for (i, input) in self.ins.iter().enumerate() {
+                    if i > 0 { f.write_str(", ")?; }
+                    write!(f, "{}: {}", input.name, Term(&input.link, input.value))?;
+                }
+                f.write_str(")")
+            },
+        }
+    }
+}
+
+/// Link with value, controls are referred by plain name
+struct Term<'a>(&'a Link, Option<Value>);
+
+impl<'a> fmt::Display for Term<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self.0 {
+            Link::Ctrl { ref name } => f.write_str(name)?,
+            ref link => write!(f, "{}", link)?,
+        }
+        match self.1 {
+            Some(value) => write!(f, " ({})", value),
+            None => f.write_str(" (?)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dsl::{Mesh, Link, NodeDecls, compile};
+    use ops::{basic_ops};
+    use serde_json::{from_str};
+    use futures::{Stream};
+    use futures::stream::{empty};
+    use tokio::executor::current_thread::{block_on_all};
+
+    #[test]
+    fn test_explain() {
+        let ops = NodeDecls::new().with(basic_ops);
+
+        let mesh: Mesh = from_str(r#"{
+  "nodes": [
+    { "name": "mul", "kind": "*", "ins": [
+      { "name": "a", "link": { "name": "a" } },
+      { "name": "b", "link": { "name": "b" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "add", "kind": "+", "ins": [
+      { "name": "a", "link": { "node": "mul", "out": "=" } },
+      { "name": "b", "link": { "name": "c" } }
+    ], "outs": [
+      { "name": "=" }
+    ] }
+  ],
+  "ctrls": [
+    { "name": "a", "value": "2" },
+    { "name": "b", "value": "3" },
+    { "name": "c", "value": "1" }
+  ]
+}"#).unwrap();
+
+        let mut compiled = compile(&ops, &mesh, Box::new(empty())).unwrap();
+        let out = compiled.subscribe_all();
+        compiled.shutdown();
+        block_on_all(out.collect()).unwrap();
+
+        let explanation = compiled.explain(&Link::output("add", "=")).unwrap();
+
+        assert_eq!(explanation.to_string(), "add.= = mul.= (6) + c (1); mul.= = a (2) * b (3)");
+
+        let json = explanation.to_json();
+        assert_eq!(json["value"], json!("7"));
+        assert_eq!(json["steps"][1]["kind"], json!("*"));
+        assert_eq!(json["steps"][1]["ins"][0], json!({ "name": "a", "link": { "name": "a" }, "value": "2" }));
+
+        assert_eq!(compiled.explain(&Link::ctrl("c")).unwrap().to_string(), "c (1)");
+        assert!(compiled.explain(&Link::output("add", "-")).is_err());
+    }
+}
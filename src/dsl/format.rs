@@ -0,0 +1,225 @@
+use std::fs::{File};
+use std::io::{Read, Write};
+use std::path::{Path};
+
+use serde::{Serialize};
+use serde::de::{DeserializeOwned};
+use serde_json;
+use serde_yaml;
+use toml;
+use rmp_serde;
+use serde_cbor;
+
+use dsl::{Mesh};
+
+/// Serialization format of mesh documents
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Yaml,
+    Toml,
+    MsgPack,
+    Cbor,
+}
+
+impl Format {
+    /// Guess format by file extension
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let ext = path.as_ref().extension()?.to_str()?.to_lowercase();
+
+        Some(match ext.as_str() {
+            "json" => Format::Json,
+            "yaml" | "yml" => Format::Yaml,
+            "toml" => Format::Toml,
+            "msgpack" | "mpk" => Format::MsgPack,
+            "cbor" => Format::Cbor,
+            _ => return None,
+        })
+    }
+
+    /// Guess format by document content
+    ///
+    /// Binary formats are recognized by the leading map marker,
+    /// text formats by the first meaningful character or line.
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        let first = *data.first()?;
+
+        // CBOR self-described tag or map
+        if data.starts_with(&[0xd9, 0xd9, 0xf7]) || (0xa0..=0xbf).contains(&first) {
+            return Some(Format::Cbor);
+        }
+
+        // MessagePack map
+        if (0x80..=0x8f).contains(&first) || first == 0xde || first == 0xdf {
+            return Some(Format::MsgPack);
+        }
+
+        let text = ::std::str::from_utf8(data).ok()?;
+
+        if text.trim_start().starts_with('{') {
+            return Some(Format::Json);
+        }
+
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line == "---" || line.starts_with('-') {
+                return Some(Format::Yaml);
+            }
+            if line.starts_with('[') {
+                return Some(Format::Toml);
+            }
+            if let Some(pos) = line.find(|c: char| c == '=' || c == ':') {
+                return Some(if &line[pos..pos + 1] == "=" { Format::Toml } else { Format::Yaml });
+            }
+            break;
+        }
+
+        None
+    }
+
+    /// Deserialize value from document of this format
+    pub fn deserialize<T: DeserializeOwned>(self, data: &[u8]) -> Result<T, String> {
+        match self {
+            Format::Json => serde_json::from_slice(data).map_err(|err| err.to_string()),
+            Format::Yaml => serde_yaml::from_slice(data).map_err(|err| err.to_string()),
+            Format::Toml => toml::from_slice(data).map_err(|err| err.to_string()),
+            Format::MsgPack => rmp_serde::from_slice(data).map_err(|err| err.to_string()),
+            Format::Cbor => serde_cbor::from_slice(data).map_err(|err| err.to_string()),
+        }
+    }
+
+    /// Serialize value to document of this format
+    ///
+    /// Structs are always encoded as maps, so documents remain self-describing.
+    pub fn serialize<T: Serialize>(self, value: &T) -> Result<Vec<u8>, String> {
+        match self {
+            Format::Json => serde_json::to_vec_pretty(value).map_err(|err| err.to_string()),
+            Format::Yaml => serde_yaml::to_vec(value).map_err(|err| err.to_string()),
+            Format::Toml => toml::to_vec(value).map_err(|err| err.to_string()),
+            Format::MsgPack => rmp_serde::to_vec_named(value).map_err(|err| err.to_string()),
+            Format::Cbor => serde_cbor::to_vec(value).map_err(|err| err.to_string()),
+        }
+    }
+}
+
+impl Mesh {
+    /// Load mesh from file
+    ///
+    /// The format is determined by file extension or by content when extension is unknown.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let mut data = Vec::new();
+
+        File::open(path)
+            .and_then(|mut file| file.read_to_end(&mut data))
+            .map_err(|err| format!("Unable to read `{}`: {}", path.display(), err))?;
+
+        match Format::from_path(path) {
+            Some(format) => Self::from_slice_as(format, &data),
+            None => Self::from_slice(&data),
+        }
+    }
+
+    /// Save mesh to file using format determined by file extension
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        let format = Format::from_path(path)
+            .ok_or_else(|| format!("Unknown mesh format of `{}`", path.display()))?;
+        let data = self.to_vec(format)?;
+
+        File::create(path)
+            .and_then(|mut file| file.write_all(&data))
+            .map_err(|err| format!("Unable to write `{}`: {}", path.display(), err))
+    }
+
+    /// Parse mesh from document of detected format
+    pub fn from_slice(data: &[u8]) -> Result<Self, String> {
+        let format = Format::detect(data).ok_or_else(|| String::from("Unknown mesh format"))?;
+        Self::from_slice_as(format, data)
+    }
+
+    /// Parse mesh from document of specified format
+    pub fn from_slice_as(format: Format, data: &[u8]) -> Result<Self, String> {
+        format.deserialize(data)
+    }
+
+    /// Serialize mesh to document of specified format
+    pub fn to_vec(&self, format: Format) -> Result<Vec<u8>, String> {
+        format.serialize(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Format};
+    use dsl::{Mesh, Link};
+    use serde_json::{from_str};
+
+    fn sample_mesh() -> Mesh {
+        from_str(r#"{
+  "nodes": [
+    { "name": "mul", "kind": "*", "info": "product", "ins": [
+      { "name": "a", "link": { "name": "a" } },
+      { "name": "b", "link": { "name": "b" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "add", "kind": "+", "ins": [
+      { "name": "a", "link": { "node": "mul", "out": "=" } },
+      { "name": "b", "link": { "name": "c" } }
+    ], "outs": [
+      { "name": "=" }
+    ] }
+  ],
+  "ctrls": [
+    { "name": "a", "value": "2.50" },
+    { "name": "b", "value": "-1.5E+3" },
+    { "name": "c", "value": "0.000001" }
+  ]
+}"#).unwrap()
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(Format::from_path("mesh.json"), Some(Format::Json));
+        assert_eq!(Format::from_path("conf/mesh.YML"), Some(Format::Yaml));
+        assert_eq!(Format::from_path("mesh.toml"), Some(Format::Toml));
+        assert_eq!(Format::from_path("mesh.msgpack"), Some(Format::MsgPack));
+        assert_eq!(Format::from_path("mesh.cbor"), Some(Format::Cbor));
+        assert_eq!(Format::from_path("mesh"), None);
+        assert_eq!(Format::from_path("mesh.txt"), None);
+    }
+
+    #[test]
+    fn test_format_round_trip() {
+        let mesh = sample_mesh();
+
+        for &format in &[Format::Json, Format::Yaml, Format::Toml, Format::MsgPack, Format::Cbor] {
+            let data = mesh.to_vec(format).unwrap();
+
+            assert_eq!(Format::detect(&data), Some(format));
+
+            let loaded = Mesh::from_slice(&data).unwrap();
+
+            assert_eq!(loaded, mesh, "{:?}", format);
+
+            assert_eq!(loaded.nodes[1].ins[0].link, Link::output("mul", "="), "{:?}", format);
+            assert_eq!(loaded.nodes[1].ins[1].link, Link::ctrl("c"), "{:?}", format);
+
+            assert_eq!(loaded.ctrls.iter().map(|ctrl| ctrl.value.to_string()).collect::<Vec<_>>(),
+                       vec!["2.50", "-1.5E+3", "0.000001"], "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn test_format_detect_text() {
+        assert_eq!(Format::detect(b"  {\"nodes\": [], \"ctrls\": []}"), Some(Format::Json));
+        assert_eq!(Format::detect(b"# mesh\nnodes: []\nctrls: []\n"), Some(Format::Yaml));
+        assert_eq!(Format::detect(b"---\nnodes: []\n"), Some(Format::Yaml));
+        assert_eq!(Format::detect(b"nodes = []\nctrls = []\n"), Some(Format::Toml));
+        assert_eq!(Format::detect(b"[[ctrls]]\nname = \"a\"\n"), Some(Format::Toml));
+        assert_eq!(Format::detect(b""), None);
+    }
+}
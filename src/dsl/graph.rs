@@ -0,0 +1,335 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use dsl::{Mesh, Node, Link};
+
+/// Set of mesh elements found by dependency query
+///
+/// Elements are listed in the order they appear in the mesh.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Deps {
+    /// control names
+    pub ctrls: Vec<String>,
+    /// node names
+    pub nodes: Vec<String>,
+    /// node outputs
+    pub links: Vec<Link>,
+}
+
+/// Dependency graph between mesh nodes
+///
+/// Inputs linked to missing nodes or controls are ignored here,
+/// those are reported by validation.
+struct Graph<'a> {
+    mesh: &'a Mesh,
+    /// node indexes by name
+    index: HashMap<&'a str, usize>,
+    /// nodes which outputs are used by node
+    deps: Vec<Vec<usize>>,
+    /// nodes which use outputs of node
+    users: Vec<Vec<usize>>,
+    /// nodes which use control
+    ctrl_users: HashMap<&'a str, Vec<usize>>,
+}
+
+impl<'a> Graph<'a> {
+    fn new(mesh: &'a Mesh) -> Self {
+        let mut index = HashMap::with_capacity(mesh.nodes.len());
+        for (i, node) in mesh.nodes.iter().enumerate() {
+            index.entry(node.name.as_str()).or_insert(i);
+        }
+
+        let mut deps = vec![Vec::new(); mesh.nodes.len()];
+        let mut users = vec![Vec::new(); mesh.nodes.len()];
+        let mut ctrl_users: HashMap<&str, Vec<usize>> = HashMap::new();
+
+        for (i, node) in mesh.nodes.iter().enumerate() {
+            for input in &node.ins {
+                match input.link {
+                    Link::Output { node: ref link_node, .. } => {
+                        if let Some(&j) = index.get(link_node.as_str()) {
+                            if !deps[i].contains(&j) {
+                                deps[i].push(j);
+                                users[j].push(i);
+                            }
+                        }
+                    },
+                    Link::Ctrl { name: ref link_ctrl } => {
+                        let list = ctrl_users.entry(link_ctrl.as_str()).or_insert_with(Vec::new);
+                        if list.last() != Some(&i) {
+                            list.push(i);
+                        }
+                    },
+                }
+            }
+        }
+
+        Self { mesh, index, deps, users, ctrl_users }
+    }
+
+    /// Kahn's topological sorting
+    fn order(&self) -> Result<Vec<usize>, String> {
+        let mut pending: Vec<_> = self.deps.iter().map(Vec::len).collect();
+        let mut ready: VecDeque<_> = (0..pending.len()).filter(|&i| pending[i] == 0).collect();
+        let mut order = Vec::with_capacity(pending.len());
+
+        while let Some(i) = ready.pop_front() {
+            order.push(i);
+            for &j in &self.users[i] {
+                pending[j] -= 1;
+                if pending[j] == 0 {
+                    ready.push_back(j);
+                }
+            }
+        }
+
+        if order.len() < pending.len() {
+            let names = (0..pending.len()).filter(|&i| pending[i] > 0)
+                .map(|i| format!("`{}`", self.mesh.nodes[i].name))
+                .collect::<Vec<_>>()
+                .join(", ");
+            return Err(format!("Cyclic dependencies between nodes {}", names));
+        }
+
+        Ok(order)
+    }
+
+    /// walk graph from nodes using given edges
+    fn closure(&self, start: Vec<usize>, edges: &[Vec<usize>]) -> Vec<bool> {
+        let mut seen = vec![false; self.mesh.nodes.len()];
+        let mut stack = start;
+
+        while let Some(i) = stack.pop() {
+            if seen[i] { continue; }
+            seen[i] = true;
+            stack.extend(edges[i].iter().cloned().filter(|&j| !seen[j]));
+        }
+
+        seen
+    }
+
+    fn deps_of(&self, seen: &[bool], with_ctrls: bool) -> Deps {
+        let mut deps = Deps::default();
+
+        for (i, node) in self.mesh.nodes.iter().enumerate() {
+            if !seen[i] { continue; }
+            deps.nodes.push(node.name.clone());
+            for output in &node.outs {
+                deps.links.push(Link::output(node.name.clone(), output.name.clone()));
+            }
+        }
+
+        if with_ctrls {
+            let mut listed = HashSet::new();
+            for ctrl in &self.mesh.ctrls {
+                let used = self.ctrl_users.get(ctrl.name.as_str())
+                    .map(|users| users.iter().any(|&i| seen[i]))
+                    .unwrap_or(false);
+                if used && listed.insert(ctrl.name.as_str()) {
+                    deps.ctrls.push(ctrl.name.clone());
+                }
+            }
+        }
+
+        deps
+    }
+}
+
+impl Mesh {
+    /// Nodes in order of instantiation, so each node follows the nodes it depends on
+    pub fn topo_order(&self) -> Result<Vec<&Node>, String> {
+        let graph = Graph::new(self);
+        Ok(graph.order()?.into_iter().map(|i| &self.nodes[i]).collect())
+    }
+
+    /// Controls and nodes which affect the value of link
+    ///
+    /// Link itself is included: the control or the node which produces it.
+    pub fn upstream(&self, link: &Link) -> Deps {
+        self.upstream_all(Some(link))
+    }
+
+    /// Controls and nodes which affect the value of any of links
+    pub fn upstream_all<'l, I: IntoIterator<Item = &'l Link>>(&self, links: I) -> Deps {
+        let graph = Graph::new(self);
+        let mut start = Vec::new();
+        let mut ctrls = Vec::new();
+
+        for link in links {
+            match *link {
+                Link::Ctrl { ref name } => ctrls.push(name.as_str()),
+                Link::Output { ref node, .. } => start.extend(graph.index.get(node.as_str())),
+            }
+        }
+
+        let seen = graph.closure(start, &graph.deps);
+        let mut deps = graph.deps_of(&seen, true);
+        deps.links.clear();
+
+        if !ctrls.is_empty() {
+            let all_ctrls = {
+                let mut wanted: HashSet<_> = deps.ctrls.iter().map(String::as_str).collect();
+                wanted.extend(ctrls);
+                let list: Vec<_> = self.ctrls.iter()
+                    .filter(|ctrl| wanted.remove(ctrl.name.as_str()))
+                    .map(|ctrl| ctrl.name.clone())
+                    .collect();
+                list
+            };
+            deps.ctrls = all_ctrls;
+        }
+
+        deps
+    }
+
+    /// Nodes and outputs which may be changed by control
+    pub fn downstream<S: AsRef<str>>(&self, ctrl: S) -> Deps {
+        let graph = Graph::new(self);
+        let start = graph.ctrl_users.get(ctrl.as_ref()).cloned().unwrap_or_default();
+        let seen = graph.closure(start, &graph.users);
+        let mut deps = graph.deps_of(&seen, false);
+
+        if self.get_ctrl(&ctrl).is_some() {
+            deps.ctrls.push(ctrl.as_ref().into());
+        }
+
+        deps
+    }
+
+    /// Depth of each node: the length of the longest chain of nodes ending with it
+    ///
+    /// Nodes which use only controls have depth 1.
+    pub fn depths(&self) -> Result<HashMap<String, usize>, String> {
+        let graph = Graph::new(self);
+        let depths = node_depths(&graph)?;
+
+        Ok(self.nodes.iter().zip(depths)
+           .map(|(node, depth)| (node.name.clone(), depth))
+           .collect())
+    }
+
+    /// The longest chain of nodes from controls to outputs
+    ///
+    /// Its length is the number of sequential computation steps of the mesh.
+    pub fn critical_path(&self) -> Result<Vec<&Node>, String> {
+        let graph = Graph::new(self);
+        let depths = node_depths(&graph)?;
+
+        let mut path = Vec::new();
+        let mut next = (0..depths.len()).max_by_key(|&i| (depths[i], ::std::cmp::Reverse(i)));
+
+        while let Some(i) = next {
+            path.push(&self.nodes[i]);
+            next = graph.deps[i].iter().cloned()
+                .max_by_key(|&j| (depths[j], ::std::cmp::Reverse(j)));
+        }
+
+        path.reverse();
+        Ok(path)
+    }
+}
+
+fn node_depths(graph: &Graph) -> Result<Vec<usize>, String> {
+    let mut depths = vec![0; graph.deps.len()];
+
+    for i in graph.order()? {
+        depths[i] = 1 + graph.deps[i].iter().map(|&j| depths[j]).max().unwrap_or(0);
+    }
+
+    Ok(depths)
+}
+
+#[cfg(test)]
+mod test {
+    use dsl::{Mesh, Link};
+    use serde_json::{from_str};
+
+    fn sample_mesh() -> Mesh {
+        from_str(r#"{
+  "nodes": [
+    { "name": "add", "kind": "+", "ins": [
+      { "name": "a", "link": { "node": "mul", "out": "=" } },
+      { "name": "b", "link": { "name": "c" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "mul", "kind": "*", "ins": [
+      { "name": "a", "link": { "name": "a" } },
+      { "name": "b", "link": { "name": "b" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "neg", "kind": "-", "ins": [
+      { "name": "a", "link": { "name": "c" } }
+    ], "outs": [
+      { "name": "=" }
+    ] }
+  ],
+  "ctrls": [
+    { "name": "a", "value": "2" },
+    { "name": "b", "value": "3" },
+    { "name": "c", "value": "1" },
+    { "name": "d", "value": "0" }
+  ]
+}"#).unwrap()
+    }
+
+    fn names<'a, I: IntoIterator<Item = &'a ::dsl::Node>>(nodes: I) -> Vec<&'a str> {
+        nodes.into_iter().map(|node| node.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_topo_order() {
+        let mesh = sample_mesh();
+
+        assert_eq!(names(mesh.topo_order().unwrap()), vec!["mul", "neg", "add"]);
+    }
+
+    #[test]
+    fn test_topo_order_cyclic() {
+        let mut mesh = sample_mesh();
+        mesh.nodes[1].ins[0].link = Link::output("add", "=");
+
+        assert_eq!(mesh.topo_order().unwrap_err(), "Cyclic dependencies between nodes `add`, `mul`");
+    }
+
+    #[test]
+    fn test_upstream() {
+        let mesh = sample_mesh();
+
+        let deps = mesh.upstream(&Link::output("add", "="));
+        assert_eq!(deps.ctrls, vec!["a", "b", "c"]);
+        assert_eq!(deps.nodes, vec!["add", "mul"]);
+
+        let deps = mesh.upstream(&Link::ctrl("d"));
+        assert_eq!(deps.ctrls, vec!["d"]);
+        assert!(deps.nodes.is_empty());
+    }
+
+    #[test]
+    fn test_downstream() {
+        let mesh = sample_mesh();
+
+        let deps = mesh.downstream("a");
+        assert_eq!(deps.nodes, vec!["add", "mul"]);
+        assert_eq!(deps.links, vec![Link::output("add", "="), Link::output("mul", "=")]);
+
+        let deps = mesh.downstream("c");
+        assert_eq!(deps.nodes, vec!["add", "neg"]);
+
+        let deps = mesh.downstream("d");
+        assert!(deps.nodes.is_empty());
+        assert!(deps.links.is_empty());
+    }
+
+    #[test]
+    fn test_depth() {
+        let mesh = sample_mesh();
+        let depths = mesh.depths().unwrap();
+
+        assert_eq!(depths["mul"], 1);
+        assert_eq!(depths["neg"], 1);
+        assert_eq!(depths["add"], 2);
+
+        assert_eq!(names(mesh.critical_path().unwrap()), vec!["mul", "add"]);
+    }
+}
@@ -1,47 +1,131 @@
 use std::rc::{Rc};
 use std::cell::{RefCell};
 use std::collections::{HashMap};
+use std::collections::hash_map::{Values};
 
-use dsl::{NodeKind, InputKind, OutputKind, Value};
+use dsl::{NodeKind, InputKind, OutputKind, Link, Value, Cause, Expr, Dual, Interval, NodeSite, SharedStream, Driver};
 use futures::{Stream};
-use future_pubsub::unsync::{Cloneable, into_cloneable};
+use serde::{Serialize};
+use serde::de::{DeserializeOwned};
+use serde_json::{self, Value as Json};
 
 pub type ValueCell = Rc<RefCell<Option<Value>>>;
-pub type ValueStream = Box<Stream<Item = Value, Error = ()>>;
+pub type SignalStream = Box<Stream<Item = Signal, Error = ()>>;
+
+/// Value with control which caused it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signal {
+    pub value: Value,
+    /// Control which caused value, none for initial values
+    pub cause: Option<Rc<Cause>>,
+}
+
+impl Signal {
+    pub fn new<V: Into<Value>>(value: V) -> Self {
+        Self { value: value.into(), cause: None }
+    }
+
+    pub fn with_cause(mut self, cause: Option<Rc<Cause>>) -> Self {
+        self.cause = cause;
+        self
+    }
+
+    /// value computed from this one keeping its cause
+    pub fn map<F: FnOnce(Value) -> Value>(self, func: F) -> Self {
+        Self { value: func(self.value), cause: self.cause }
+    }
+}
 
+/// Output which may be read by many inputs and subscribers
+///
+/// Each stream taken from observable gets values produced after it was taken.
 #[derive(Clone)]
 pub struct Observable {
     value: ValueCell,
-    stream: Cloneable<ValueStream>,
+    stream: SharedStream<SignalStream>,
 }
 
-impl Into<(ValueCell, ValueStream)> for Observable {
-    fn into(self) -> (ValueCell, ValueStream) {
-        (self.value, Box::new(self.stream.map(|rc| *rc).map_err(|_| ())))
+impl Into<(ValueCell, SignalStream)> for Observable {
+    fn into(self) -> (ValueCell, SignalStream) {
+        let stream = self.stream();
+        (self.value, stream)
     }
 }
 
 impl<S> From<S> for Observable
-where S: Stream<Item = Value, Error = ()> + 'static
+where S: Stream<Item = Signal, Error = ()> + 'static
 {
     fn from(stream: S) -> Self {
         let value = Rc::new(RefCell::new(Option::None));
         let value2 = value.clone();
-        let stream = Box::new(stream.map(move |val| {
-            *value2.borrow_mut() = Some(val);
-            val
+        let stream = Box::new(stream.map(move |signal| {
+            *value2.borrow_mut() = Some(signal.value);
+            signal
         }));
-        Self { value, stream: into_cloneable(stream) }
+        Self { value, stream: SharedStream::new(stream) }
+    }
+}
+
+impl Observable {
+    /// cell which holds the last value
+    pub fn value(&self) -> ValueCell {
+        self.value.clone()
+    }
+
+    /// stream of all values
+    pub fn stream(&self) -> SignalStream {
+        Box::new(self.stream.receive())
+    }
+
+    /// stream which keeps at most `capacity` latest values until they are polled
+    pub fn latest(&self, capacity: usize) -> SignalStream {
+        Box::new(self.stream.receive_latest(capacity))
+    }
+
+    /// let driver produce values instead of streams taken from observable
+    pub fn drive(&self, driver: &Driver) {
+        driver.add(&self.stream);
+    }
+}
+
+/// Internal state of node which survives restarts
+///
+/// Stateful nodes opt in by attaching state to their outputs using `Observables::with_state`.
+pub trait NodeState {
+    fn save(&self) -> Result<Json, String>;
+    fn restore(&self, state: Json) -> Result<(), String>;
+}
+
+impl<T: Serialize + DeserializeOwned> NodeState for RefCell<T> {
+    fn save(&self) -> Result<Json, String> {
+        serde_json::to_value(&*self.borrow()).map_err(|err| err.to_string())
+    }
+
+    fn restore(&self, state: Json) -> Result<(), String> {
+        *self.borrow_mut() = serde_json::from_value(state).map_err(|err| err.to_string())?;
+        Ok(())
     }
 }
 
 pub struct Observables {
     map: HashMap<String, Observable>,
+    state: Option<Rc<NodeState>>,
 }
 
 impl Observables {
     pub fn new() -> Self {
-        Self { map: HashMap::new() }
+        Self { map: HashMap::new(), state: None }
+    }
+
+    /// attach internal state of node
+    pub fn with_state<S: NodeState + 'static>(mut self, state: Rc<S>) -> Self {
+        self.state = Some(state);
+        self
+    }
+
+    /// detach internal state of node
+    pub fn take_state(&mut self) -> Option<Rc<NodeState>> {
+        self.state.take()
     }
 
     /// use stream of values
@@ -64,19 +148,100 @@ impl Observables {
 /// Instantiatiate node
 pub type NodeInst = fn(Observables) -> Observables;
 
+/// Evaluate node outputs from input values
+///
+/// Inputs and outputs follow the order of the kind declaration.
+pub type NodeEval = fn(&[Value]) -> Vec<Value>;
+
+/// Express node outputs by symbolic expressions of inputs
+///
+/// Inputs and outputs follow the order of the kind declaration.
+pub type NodeSymbolic = fn(&[Expr]) -> Vec<Expr>;
+
+/// Evaluate node outputs with derivatives from dual inputs
+///
+/// Inputs and outputs follow the order of the kind declaration.
+pub type NodeDeriv = fn(&[Dual]) -> Vec<Dual>;
+
+/// Evaluate ranges of node outputs from ranges of inputs
+///
+/// Inputs and outputs follow the order of the kind declaration.
+/// Error reports inputs which may be outside of the domain of kind.
+pub type NodeInterval = fn(&[Interval]) -> Result<Vec<Interval>, String>;
+
+/// Find output of node which is equal to some other link
+///
+/// Returns name of output, link to use instead and name of rule.
+pub type NodeIdentity = fn(&NodeSite) -> Option<(&'static str, Link, &'static str)>;
+
 pub struct NodeDecl {
     pub def: NodeKind,
     pub imp: NodeInst,
+    eval: Option<NodeEval>,
+    symbolic: Option<NodeSymbolic>,
+    deriv: Option<NodeDeriv>,
+    interval: Option<NodeInterval>,
+    identity: Option<NodeIdentity>,
 }
 
 impl NodeDecl {
     pub fn new(def: NodeKind, imp: NodeInst) -> Self {
-        Self { def, imp }
+        Self { def, imp, eval: None, symbolic: None, deriv: None, interval: None, identity: None }
+    }
+
+    pub fn with_eval(mut self, eval: NodeEval) -> Self {
+        self.eval = Some(eval);
+        self
+    }
+
+    pub fn with_symbolic(mut self, symbolic: NodeSymbolic) -> Self {
+        self.symbolic = Some(symbolic);
+        self
+    }
+
+    pub fn with_deriv(mut self, deriv: NodeDeriv) -> Self {
+        self.deriv = Some(deriv);
+        self
+    }
+
+    pub fn with_interval(mut self, interval: NodeInterval) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+
+    pub fn with_identity(mut self, identity: NodeIdentity) -> Self {
+        self.identity = Some(identity);
+        self
     }
 
     pub fn imp(&self, ins: Observables) -> Observables {
         (self.imp)(ins)
     }
+
+    pub fn eval(&self, ins: &[Value]) -> Option<Vec<Value>> {
+        self.eval.map(|eval| eval(ins))
+    }
+
+    /// Evaluation function to call without looking up declaration
+    pub fn eval_fn(&self) -> Option<NodeEval> {
+        self.eval
+    }
+
+    pub fn symbolic(&self, ins: &[Expr]) -> Option<Vec<Expr>> {
+        self.symbolic.map(|symbolic| symbolic(ins))
+    }
+
+    pub fn deriv(&self, ins: &[Dual]) -> Option<Vec<Dual>> {
+        self.deriv.map(|deriv| deriv(ins))
+    }
+
+    pub fn interval(&self, ins: &[Interval]) -> Option<Result<Vec<Interval>, String>> {
+        self.interval.map(|interval| interval(ins))
+    }
+
+    pub fn identity(&self, site: &NodeSite) -> Option<(&'static str, Link, &'static str)> {
+        self.identity.and_then(|identity| identity(site))
+    }
 }
 
 /// Nodes declarations registry
@@ -110,11 +275,18 @@ impl NodeDecls {
     pub fn get<S: AsRef<str>>(&self, name: S) -> Option<&NodeDecl> {
         self.decls.get(name.as_ref().into())
     }
+
+    /// iterate over declarations in arbitrary order
+    pub fn iter(&self) -> Values<String, NodeDecl> {
+        self.decls.values()
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use super::{Observables, NodeDecl, NodeKind, InputKind, OutputKind, NodeDecls};
+    use std::cell::{RefCell};
+    use std::collections::{HashMap};
+    use super::{Observables, NodeDecl, NodeKind, InputKind, OutputKind, NodeDecls, NodeState, Signal};
     use futures::{Future, Sink, Stream};
     use futures::future::{lazy};
     use futures::unsync::mpsc::{unbounded};
@@ -126,9 +298,9 @@ mod test {
             let (av, ai) = ins.get("a").into();
             let (bv, bi) = ins.get("b").into();
             
-            let ro = Box::new(ai.map(|_| ()).select(bi.map(|_| ())).map(move |_| {
+            let ro = Box::new(ai.map(|a| a.cause).select(bi.map(|b| b.cause)).map(move |cause| {
                 match (*av.borrow(), *bv.borrow()) {
-                    (Some(a), Some(b)) => Some(a + b),
+                    (Some(a), Some(b)) => Some(Signal::new(a + b).with_cause(cause)),
                     _ => None,
                 }
             }).skip_while(|opt| Ok(opt.is_none())).map(Option::unwrap));
@@ -157,21 +329,35 @@ mod test {
 
         block_on_all(lazy(|| {
             println!("send b: 1");
-            spawn(sb.send(1.into()).map(|_sb| { println!("sent b: 1"); () }).map_err(|_| ()));
+            spawn(sb.send(Signal::new(1)).map(|_sb| { println!("sent b: 1"); () }).map_err(|_| ()));
 
             let v2 = v.clone();
             spawn(r.map(move |r| {
                 println!("recv =: {}", v2.borrow().unwrap());
-                r
+                r.value
             }).collect().map(move |rv| {
                 assert_eq!(rv, vec![1.into(), 2.into()]);
                 assert_eq!(*v.borrow(), Some(2.into()));
             }));
 
             println!("send a: 0");
-            spawn(sa.send(0.into()).and_then(|sa| { println!("sent a: 0"); println!("send a: 1"); sa.send(1.into()) }).map(|_sa| { println!("sent a: 1"); () }).map_err(|_| ()));
+            spawn(sa.send(Signal::new(0)).and_then(|sa| { println!("sent a: 0"); println!("send a: 1"); sa.send(Signal::new(1)) }).map(|_sa| { println!("sent a: 1"); () }).map_err(|_| ()));
 
             Ok::<_, ()>(())
         })).unwrap();
     }
+
+    #[test]
+    fn test_node_state() {
+        let state = RefCell::new(vec![1, 2]);
+
+        assert_eq!(state.save(), Ok(json!([1, 2])));
+        assert!(state.restore(json!([3])).is_ok());
+        assert_eq!(*state.borrow(), vec![3]);
+        assert!(state.restore(json!("three")).is_err());
+
+        // JSON keys must be strings
+        let state = RefCell::new(vec![(vec![1], 2)].into_iter().collect::<HashMap<_, _>>());
+        assert!(state.save().is_err());
+    }
 }
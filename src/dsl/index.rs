@@ -0,0 +1,84 @@
+use std::collections::{HashMap};
+
+use dsl::{Mesh, Node, Ctrl, Export};
+
+/// Lookup tables of mesh elements by name
+///
+/// Unlike `Mesh::get_node` and others it finds elements in constant time.
+/// When names are duplicated the first element wins, as with linear search.
+pub struct MeshIndex<'a> {
+    nodes: HashMap<&'a str, &'a Node>,
+    ctrls: HashMap<&'a str, &'a Ctrl>,
+    exports: HashMap<&'a str, &'a Export>,
+}
+
+impl<'a> MeshIndex<'a> {
+    pub fn new(mesh: &'a Mesh) -> Self {
+        let mut nodes = HashMap::with_capacity(mesh.nodes.len());
+        for node in &mesh.nodes {
+            nodes.entry(node.name.as_str()).or_insert(node);
+        }
+
+        let mut ctrls = HashMap::with_capacity(mesh.ctrls.len());
+        for ctrl in &mesh.ctrls {
+            ctrls.entry(ctrl.name.as_str()).or_insert(ctrl);
+        }
+
+        let mut exports = HashMap::with_capacity(mesh.exports.len());
+        for export in &mesh.exports {
+            exports.entry(export.name.as_str()).or_insert(export);
+        }
+
+        Self { nodes, ctrls, exports }
+    }
+
+    pub fn get_node<S: AsRef<str>>(&self, name: S) -> Option<&'a Node> {
+        self.nodes.get(name.as_ref()).cloned()
+    }
+
+    pub fn get_ctrl<S: AsRef<str>>(&self, name: S) -> Option<&'a Ctrl> {
+        self.ctrls.get(name.as_ref()).cloned()
+    }
+
+    pub fn get_export<S: AsRef<str>>(&self, name: S) -> Option<&'a Export> {
+        self.exports.get(name.as_ref()).cloned()
+    }
+}
+
+impl Mesh {
+    /// Build lookup tables for fast access by name
+    pub fn index(&self) -> MeshIndex {
+        MeshIndex::new(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dsl::{Mesh};
+    use serde_json::{from_str};
+
+    #[test]
+    fn test_mesh_index() {
+        let mesh: Mesh = from_str(r#"{
+  "nodes": [
+    { "name": "neg", "kind": "-", "ins": [
+      { "name": "a", "link": { "name": "a" } }
+    ] },
+    { "name": "neg", "kind": "^-1" }
+  ],
+  "ctrls": [
+    { "name": "a", "value": "1" }
+  ],
+  "exports": [
+    { "name": "neg", "link": { "node": "neg", "out": "=" } }
+  ]
+}"#).unwrap();
+
+        let index = mesh.index();
+
+        assert_eq!(index.get_node("neg").map(|node| node.kind.as_str()), Some("-"));
+        assert!(index.get_node("inv").is_none());
+        assert_eq!(index.get_ctrl("a").map(|ctrl| ctrl.value), Some(1.into()));
+        assert!(index.get_export("neg").is_some());
+    }
+}
@@ -0,0 +1,195 @@
+use std::rc::{Rc};
+use std::cell::{RefCell};
+use std::collections::{HashMap, VecDeque};
+
+use futures::{Stream, Poll, Async};
+use futures::task::{self, Task};
+
+use dsl::{Mesh, InputControl, ControlStream, Journal};
+
+/// Entry of controls into running mesh
+///
+/// Controls are validated, stamped and journaled when they are accepted,
+/// then delivered to nodes in the same order.
+pub struct Intake {
+    /// Names of controls which may be changed
+    ctrls: HashMap<String, bool>,
+    ids: u64,
+    journal: Option<Journal>,
+    queue: VecDeque<InputControl>,
+    task: Option<Task>,
+    closed: bool,
+    /// Callbacks which see every accepted control
+    observers: Vec<Box<Fn(&InputControl)>>,
+}
+
+impl Intake {
+    pub fn new(mesh: &Mesh) -> Self {
+        Self {
+            ctrls: mesh.ctrls.iter().map(|ctrl| (ctrl.name.clone(), ctrl.is_constant())).collect(),
+            ids: 0,
+            journal: None,
+            queue: VecDeque::new(),
+            task: None,
+            closed: false,
+            observers: Vec::new(),
+        }
+    }
+
+    /// journal which accepted controls are appended to
+    pub fn with_journal(mut self, journal: Journal) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// check that control exists and may be changed
+    pub fn check(&self, name: &str) -> Result<(), String> {
+        match self.ctrls.get(name) {
+            None => Err(format!("Unknown control `{}`", name)),
+            Some(&true) => Err(format!("Control `{}` is constant", name)),
+            Some(&false) => Ok(()),
+        }
+    }
+
+    /// stamp control with the next change ID and append it to journal
+    ///
+    /// IDs set by caller are replaced, so every change is attributed to the control which mesh accepted.
+    fn admit(&mut self, ctrl: InputControl) -> Result<InputControl, String> {
+        let ctrl = ctrl.stamp(self.ids + 1);
+        if let Some(ref mut journal) = self.journal {
+            journal.append(&ctrl)?;
+        }
+        self.ids = ctrl.id;
+        for observer in &self.observers {
+            observer(&ctrl);
+        }
+        Ok(ctrl)
+    }
+
+    /// call function for each control accepted from now on
+    ///
+    /// Controls replayed from journal are not observed, since they were accepted before.
+    pub fn observe<F: Fn(&InputControl) + 'static>(&mut self, observer: F) {
+        self.observers.push(Box::new(observer));
+    }
+
+    fn enqueue(&mut self, ctrl: InputControl) -> u64 {
+        let id = ctrl.id;
+        self.queue.push_back(ctrl);
+        if let Some(task) = self.task.take() {
+            task.notify();
+        }
+        id
+    }
+
+    /// accept control and queue it for delivery
+    ///
+    /// Returns change ID which is carried by `OutputChange`s caused by control.
+    pub fn push(&mut self, ctrl: InputControl) -> Result<u64, String> {
+        self.check(&ctrl.name)?;
+        if self.closed {
+            return Err("Mesh is shut down".into());
+        }
+        let ctrl = self.admit(ctrl)?;
+        Ok(self.enqueue(ctrl))
+    }
+
+    /// accept control which is already journaled
+    ///
+    /// It keeps change ID and time which were stamped when it was accepted first,
+    /// controls from journals without IDs get the next ones.
+    pub fn replay(&mut self, ctrl: InputControl) -> Result<u64, String> {
+        self.check(&ctrl.name)?;
+        let ctrl = if ctrl.id != 0 { ctrl } else { InputControl { id: self.ids + 1, ..ctrl } };
+        self.ids = self.ids.max(ctrl.id);
+        Ok(self.enqueue(ctrl))
+    }
+
+    /// stop accepting controls, queued ones are still delivered
+    pub fn close(&mut self) {
+        self.closed = true;
+        if let Some(task) = self.task.take() {
+            task.notify();
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// number of controls which wait for delivery
+    pub fn queued(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// rewrite journal keeping only the latest entry of each control
+    pub fn compact_journal(&mut self) -> Result<(), String> {
+        match self.journal {
+            Some(ref mut journal) => journal.compact(),
+            None => Err("Mesh is not journaled".into()),
+        }
+    }
+}
+
+/// Stream of accepted controls
+///
+/// Controls from `external` stream are accepted when they are received, invalid ones are skipped.
+/// Stream ends when `external` ends, intake is closed and queued controls are delivered.
+/// Failure to journal control ends it with error.
+pub struct Accepted {
+    intake: Rc<RefCell<Intake>>,
+    external: Option<ControlStream>,
+}
+
+impl Accepted {
+    pub fn new(intake: Rc<RefCell<Intake>>, external: ControlStream) -> Self {
+        Self { intake, external: Some(external) }
+    }
+}
+
+impl Stream for Accepted {
+    type Item = InputControl;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Option<InputControl>, ()> {
+        loop {
+            if let Some(ctrl) = self.intake.borrow_mut().queue.pop_front() {
+                return Ok(Async::Ready(Some(ctrl)));
+            }
+
+            let polled = match self.external {
+                Some(ref mut external) => external.poll()?,
+                None => Async::NotReady,
+            };
+
+            match polled {
+                Async::Ready(Some(ctrl)) => {
+                    let mut intake = self.intake.borrow_mut();
+                    if let Err(err) = intake.check(&ctrl.name) {
+                        warn!("Skip control: {}", err);
+                        continue;
+                    }
+                    return match intake.admit(ctrl) {
+                        Ok(ctrl) => Ok(Async::Ready(Some(ctrl))),
+                        Err(err) => {
+                            error!("{}", err);
+                            Err(())
+                        },
+                    };
+                },
+                Async::Ready(None) => {
+                    self.external = None;
+                    continue;
+                },
+                Async::NotReady => (),
+            }
+
+            let mut intake = self.intake.borrow_mut();
+            if intake.closed && self.external.is_none() {
+                return Ok(Async::Ready(None));
+            }
+            intake.task = Some(task::current());
+            return Ok(Async::NotReady);
+        }
+    }
+}
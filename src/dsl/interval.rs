@@ -0,0 +1,300 @@
+use std::fmt;
+use std::collections::{HashMap, HashSet};
+
+use dsl::{Mesh, Link, Value, NodeDecls};
+
+/// Closed range of values, bounds may be infinite
+///
+/// Operations round bounds outwards, so results enclose all exact values.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Interval {
+    #[serde(with = "::dsl::value")]
+    pub min: Value,
+    #[serde(with = "::dsl::value")]
+    pub max: Value,
+}
+
+fn infinity() -> Value {
+    "Infinity".parse().unwrap()
+}
+
+/// Number of significant digits of value
+///
+/// Rounded results always take all of them, so results with fewer digits are exact.
+const PRECISION: u64 = 34;
+
+/// computed lower bound moved down when it may be rounded
+fn down(value: Value) -> Value {
+    if value.digits() < PRECISION { value } else { value.next_minus() }
+}
+
+/// computed upper bound moved up when it may be rounded
+fn up(value: Value) -> Value {
+    if value.digits() < PRECISION { value } else { value.next_plus() }
+}
+
+/// product of bounds where zero times infinity is zero
+fn mul_bound(a: Value, b: Value) -> Value {
+    if a.is_zero() || b.is_zero() { Value::from(0) } else { a * b }
+}
+
+impl Interval {
+    pub fn new(min: Value, max: Value) -> Self {
+        Self { min, max }
+    }
+
+    pub fn point(value: Value) -> Self {
+        Self::new(value, value)
+    }
+
+    /// any value
+    pub fn unbounded() -> Self {
+        Self::new(-infinity(), infinity())
+    }
+
+    pub fn contains(&self, value: Value) -> bool {
+        self.min <= value && value <= self.max
+    }
+
+    pub fn neg(&self) -> Self {
+        Self::new(-self.max, -self.min)
+    }
+
+    pub fn add(&self, other: &Interval) -> Self {
+        Self::new(down(self.min + other.min), up(self.max + other.max))
+    }
+
+    pub fn mul(&self, other: &Interval) -> Self {
+        let products = [
+            mul_bound(self.min, other.min),
+            mul_bound(self.min, other.max),
+            mul_bound(self.max, other.min),
+            mul_bound(self.max, other.max),
+        ];
+        let min = products.iter().fold(products[0], |a, &b| if b < a { b } else { a });
+        let max = products.iter().fold(products[0], |a, &b| if b > a { b } else { a });
+        Self::new(down(min), up(max))
+    }
+
+    /// fails when interval contains zero
+    pub fn inv(&self) -> Result<Self, String> {
+        if self.contains(Value::from(0)) {
+            return Err(format!("Divisor {} contains zero", self));
+        }
+        let one = Value::from(1);
+        Ok(Self::new(down(one / self.max), up(one / self.min)))
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}, {}]", self.min, self.max)
+    }
+}
+
+/// Node which inputs may be outside of the domain of its kind
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hazard {
+    pub node: String,
+    pub kind: String,
+    pub message: String,
+}
+
+impl fmt::Display for Hazard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Node `{}` ({}): {}", self.node, self.kind, self.message)
+    }
+}
+
+/// Guaranteed ranges of node outputs
+///
+/// Each range encloses every value the output may take, bounds are rounded outwards.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Ranges {
+    /// Outputs of nodes in the order they appear in the mesh
+    pub ranges: Vec<(Link, Interval)>,
+    pub hazards: Vec<Hazard>,
+}
+
+impl Ranges {
+    pub fn get(&self, link: &Link) -> Option<Interval> {
+        self.ranges.iter().find(|&&(ref range_link, _)| range_link == link).map(|&(_, interval)| interval)
+    }
+}
+
+impl Mesh {
+    /// Propagate ranges of controls through the mesh without running it
+    ///
+    /// Controls take ranges from `bounds`, missing and constant ones are fixed at their values.
+    /// Outputs of kinds without interval semantics and of hazardous nodes are unbounded.
+    /// Only root causes are reported, nodes downstream of hazardous ones are not.
+    pub fn ranges(&self, decls: &NodeDecls, bounds: &HashMap<String, Interval>) -> Result<Ranges, String> {
+        for (name, bound) in bounds {
+            if self.get_ctrl(name).is_none() {
+                return Err(format!("Unknown control `{}`", name));
+            }
+            if !(bound.min <= bound.max) {
+                return Err(format!("Invalid bounds {} of control `{}`", bound, name));
+            }
+        }
+
+        let mut intervals: HashMap<Link, Interval> = self.ctrls.iter().map(|ctrl| {
+            let interval = match bounds.get(&ctrl.name) {
+                Some(bound) if !ctrl.is_constant() => *bound,
+                _ => Interval::point(ctrl.value),
+            };
+            (Link::ctrl(ctrl.name.clone()), interval)
+        }).collect();
+
+        let mut hazards = Vec::new();
+        // outputs which depend on hazardous nodes
+        let mut tainted: HashSet<Link> = HashSet::new();
+
+        for node in self.topo_order()? {
+            let decl = decls.get(&node.kind)
+                .ok_or_else(|| format!("Unsupported node kind `{}`", node.kind))?;
+
+            let args = decl.def.ins.iter().map(|kind| {
+                node.get_in(&kind.name)
+                    .and_then(|input| intervals.get(&input.link).cloned())
+                    .ok_or_else(|| format!("Missing input `{}` of node `{}`", kind.name, node.name))
+            }).collect::<Result<Vec<_>, String>>()?;
+
+            let derived = node.ins.iter().any(|input| tainted.contains(&input.link));
+
+            let (outs, hazardous) = match decl.interval(&args) {
+                Some(Ok(outs)) => (outs, derived),
+                Some(Err(message)) => {
+                    if !derived {
+                        hazards.push(Hazard { node: node.name.clone(), kind: node.kind.clone(), message });
+                    }
+                    (Vec::new(), true)
+                },
+                None => (Vec::new(), derived),
+            };
+
+            for (i, kind) in decl.def.outs.iter().enumerate() {
+                let link = Link::output(node.name.clone(), kind.name.clone());
+                let interval = outs.get(i).cloned().unwrap_or_else(Interval::unbounded);
+                if hazardous {
+                    tainted.insert(link.clone());
+                }
+                intervals.insert(link, interval);
+            }
+        }
+
+        let ranges = self.nodes.iter()
+            .flat_map(|node| node.outs.iter().map(move |output| Link::output(node.name.clone(), output.name.clone())))
+            .filter_map(|link| intervals.get(&link).cloned().map(|interval| (link, interval)))
+            .collect();
+
+        Ok(Ranges { ranges, hazards })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{HashMap};
+    use super::{Interval};
+    use dsl::{Mesh, Link, Value, NodeDecls};
+    use dsl::testing::{opaque_ops};
+    use ops::{basic_ops};
+    use serde_json::{from_str};
+
+    #[test]
+    fn test_ranges() {
+        let ops = NodeDecls::new().with(basic_ops).with(opaque_ops);
+
+        let mesh: Mesh = from_str(r#"{
+  "nodes": [
+    { "name": "mul", "kind": "*", "ins": [
+      { "name": "a", "link": { "name": "a" } },
+      { "name": "b", "link": { "name": "b" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "add", "kind": "+", "ins": [
+      { "name": "a", "link": { "node": "mul", "out": "=" } },
+      { "name": "b", "link": { "name": "c" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "neg", "kind": "-", "ins": [
+      { "name": "a", "link": { "node": "add", "out": "=" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "inv", "kind": "^-1", "ins": [
+      { "name": "a", "link": { "node": "neg", "out": "=" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "inv2", "kind": "^-1", "ins": [
+      { "name": "a", "link": { "node": "inv", "out": "=" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "max", "kind": "max", "ins": [
+      { "name": "a", "link": { "name": "a" } },
+      { "name": "b", "link": { "name": "b" } }
+    ], "outs": [
+      { "name": "=" }
+    ] }
+  ],
+  "ctrls": [
+    { "name": "a", "value": "2" },
+    { "name": "b", "value": "3" },
+    { "name": "c", "value": "1", "const": true }
+  ]
+}"#).unwrap();
+
+        let interval = |min: i32, max: i32| Interval::new(min.into(), max.into());
+
+        let bounds: HashMap<_, _> = vec![
+            ("a".to_string(), interval(-1, 2)),
+            ("b".to_string(), interval(1, 3)),
+        ].into_iter().collect();
+
+        let ranges = mesh.ranges(&ops, &bounds).unwrap();
+
+        assert_eq!(ranges.get(&Link::output("mul", "=")), Some(interval(-3, 6)));
+        assert_eq!(ranges.get(&Link::output("add", "=")), Some(interval(-2, 7)));
+        assert_eq!(ranges.get(&Link::output("neg", "=")), Some(interval(-7, 2)));
+        assert_eq!(ranges.get(&Link::output("inv", "=")), Some(Interval::unbounded()));
+        assert_eq!(ranges.get(&Link::output("max", "=")), Some(Interval::unbounded()));
+
+        assert_eq!(ranges.get(&Link::output("inv2", "=")), Some(Interval::unbounded()));
+
+        // `inv2` fails only because of `inv`
+        assert_eq!(ranges.hazards.len(), 1);
+        assert_eq!(ranges.hazards[0].to_string(), "Node `inv` (^-1): Divisor [-7, 2] contains zero");
+
+        let bounds: HashMap<_, _> = vec![("a".to_string(), interval(1, 3))].into_iter().collect();
+        let ranges = mesh.ranges(&ops, &bounds).unwrap();
+
+        assert!(ranges.hazards.is_empty());
+        assert_eq!(ranges.get(&Link::output("inv", "=")),
+                   Some(Interval::new(Value::from(-1) / Value::from(4), Value::from(-1) / Value::from(10))));
+
+        // constant controls stay fixed
+        let bounds: HashMap<_, _> = vec![("c".to_string(), interval(-10, 10))].into_iter().collect();
+        assert!(mesh.ranges(&ops, &bounds).unwrap().hazards.is_empty());
+
+        let bounds: HashMap<_, _> = vec![("d".to_string(), interval(0, 1))].into_iter().collect();
+        assert!(mesh.ranges(&ops, &bounds).is_err());
+
+        let bounds: HashMap<_, _> = vec![("a".to_string(), interval(1, 0))].into_iter().collect();
+        assert!(mesh.ranges(&ops, &bounds).is_err());
+
+        // inexact bounds are rounded outwards
+        let third = Value::from(1) / Value::from(3);
+        let inv = interval(3, 3).inv().unwrap();
+        assert!(inv.min < third && third < inv.max);
+        assert_eq!(inv, Interval::new(third.next_minus(), third.next_plus()));
+        assert!(inv.mul(&interval(3, 3)).contains(1.into()));
+        assert_eq!(interval(1, 2).add(&interval(3, 4)), interval(4, 6));
+
+        assert_eq!(interval(-2, 3).mul(&Interval::unbounded()), Interval::unbounded());
+        assert_eq!(interval(0, 0).mul(&Interval::unbounded()), interval(0, 0));
+    }
+}
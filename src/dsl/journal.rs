@@ -0,0 +1,257 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::collections::{HashMap};
+
+use futures::stream::{iter_ok};
+use serde_json;
+
+use dsl::{Value, InputControl, ControlStream, now_millis};
+
+/// Control accepted by mesh
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// Change ID assigned by mesh, zero in journals written before IDs were stored
+    #[serde(default)]
+    pub id: u64,
+    /// Milliseconds since Unix epoch
+    pub time: u64,
+    pub name: String,
+    #[serde(with = "::dsl::value")]
+    pub value: Value,
+}
+
+impl JournalEntry {
+    pub fn new(ctrl: &InputControl) -> Self {
+        let time = if ctrl.time != 0 { ctrl.time } else { now_millis() };
+        Self { id: ctrl.id, time, name: ctrl.name.clone(), value: ctrl.value }
+    }
+
+    /// control as it was accepted
+    pub fn ctrl(&self) -> InputControl {
+        InputControl { id: self.id, time: self.time, ..InputControl::new(self.name.clone(), self.value) }
+    }
+}
+
+/// Journal of controls stored as JSON lines
+///
+/// Each accepted control is appended and synced to disk right away,
+/// so mesh can be brought back to the same state by replaying journal on startup.
+/// Controls which mesh rejects are not journaled.
+pub struct Journal {
+    path: PathBuf,
+    file: File,
+}
+
+impl Journal {
+    /// open journal for appending, creating file when it does not exist
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref().to_path_buf();
+        let file = open_append(&path)?;
+        Ok(Self { path, file })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// read all entries
+    ///
+    /// Incomplete last line, which is left by interrupted write, is skipped.
+    pub fn entries(&self) -> Result<Vec<JournalEntry>, String> {
+        let mut text = String::new();
+        File::open(&self.path)
+            .and_then(|mut file| file.read_to_string(&mut text))
+            .map_err(|err| format!("Unable to read `{}`: {}", self.path.display(), err))?;
+
+        let lines: Vec<_> = text.lines().enumerate().filter(|&(_, line)| !line.trim().is_empty()).collect();
+        let mut entries = Vec::with_capacity(lines.len());
+
+        for (i, &(number, line)) in lines.iter().enumerate() {
+            match serde_json::from_str(line) {
+                Ok(entry) => entries.push(entry),
+                Err(ref err) if i + 1 == lines.len() && !text.ends_with('\n') => {
+                    warn!("Skip incomplete entry at line {} of `{}`: {}", number + 1, self.path.display(), err);
+                },
+                Err(err) => return Err(format!("Invalid entry at line {} of `{}`: {}", number + 1, self.path.display(), err)),
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// append control
+    pub fn append(&mut self, ctrl: &InputControl) -> Result<(), String> {
+        let line = serde_json::to_string(&JournalEntry::new(ctrl)).map_err(|err| err.to_string())?;
+        writeln!(self.file, "{}", line)
+            .and_then(|_| self.file.sync_data())
+            .map_err(|err| format!("Unable to write `{}`: {}", self.path.display(), err))
+    }
+
+    /// stream of controls stored in journal
+    pub fn replay(&self) -> Result<ControlStream, String> {
+        let ctrls: Vec<_> = self.entries()?.iter().map(JournalEntry::ctrl).collect();
+        Ok(Box::new(iter_ok(ctrls)))
+    }
+
+    /// rewrite journal keeping only the latest entry of each control
+    pub fn compact(&mut self) -> Result<(), String> {
+        let entries = self.entries()?;
+
+        let latest: HashMap<_, _> = entries.iter().enumerate()
+            .map(|(i, entry)| (entry.name.as_str(), i))
+            .collect();
+
+        let mut text = String::new();
+        for (i, entry) in entries.iter().enumerate() {
+            if latest[entry.name.as_str()] != i { continue; }
+            text.push_str(&serde_json::to_string(entry).map_err(|err| err.to_string())?);
+            text.push('\n');
+        }
+
+        let mut temp = self.path.clone().into_os_string();
+        temp.push(".tmp");
+        let temp = PathBuf::from(temp);
+
+        File::create(&temp)
+            .and_then(|mut file| file.write_all(text.as_bytes()).and_then(|_| file.sync_all()))
+            .and_then(|_| fs::rename(&temp, &self.path))
+            .map_err(|err| format!("Unable to write `{}`: {}", self.path.display(), err))?;
+
+        self.file = open_append(&self.path)?;
+        Ok(())
+    }
+}
+
+fn open_append(path: &Path) -> Result<File, String> {
+    OpenOptions::new().create(true).append(true).open(path)
+        .map_err(|err| format!("Unable to open `{}`: {}", path.display(), err))
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs;
+    use std::process;
+    use super::{Journal, JournalEntry};
+    use dsl::{Mesh, Link, InputControl, Intake, NodeDecls, compile, compile_journaled};
+    use ops::{basic_ops};
+    use serde_json::{from_str};
+    use futures::{Stream};
+    use futures::stream::{empty};
+    use tokio::executor::current_thread::{block_on_all};
+
+    #[test]
+    fn test_journal() {
+        let ops = NodeDecls::new().with(basic_ops);
+
+        let mesh: Mesh = from_str(r#"{
+  "nodes": [
+    { "name": "add", "kind": "+", "ins": [
+      { "name": "a", "link": { "name": "a" } },
+      { "name": "b", "link": { "name": "b" } }
+    ], "outs": [
+      { "name": "=" }
+    ] }
+  ],
+  "ctrls": [
+    { "name": "a", "value": "1" },
+    { "name": "b", "value": "2" }
+  ]
+}"#).unwrap();
+
+        let path = env::temp_dir().join(format!("linko-journal-{}.jsonl", process::id()));
+        let _ = fs::remove_file(&path);
+        let add = Link::output("add", "=");
+
+        let mut compiled = compile_journaled(&ops, &mesh, Box::new(empty()), Journal::open(&path).unwrap()).unwrap();
+        compiled.set_ctrl("a", 3).unwrap();
+        compiled.set_ctrl("b", 4).unwrap();
+        compiled.set_ctrl("a", 5).unwrap();
+        // rejected controls are not journaled
+        assert!(compiled.set_ctrl("c", 6).is_err());
+
+        let entries = |journal: &Journal| journal.entries().unwrap().iter()
+            .map(|entry| (entry.name.clone(), entry.value)).collect::<Vec<_>>();
+
+        let mut journal = Journal::open(&path).unwrap();
+        assert_eq!(entries(&journal), vec![("a".into(), 3.into()), ("b".into(), 4.into()), ("a".into(), 5.into())]);
+
+        // journal of running mesh is compacted in place
+        compiled.compact_journal().unwrap();
+        compiled.set_ctrl("b", 1).unwrap();
+        let out = compiled.subscribe_all();
+        compiled.shutdown();
+        block_on_all(out.collect()).unwrap();
+
+        assert_eq!(compiled.get(&add), Some(6.into()));
+        assert_eq!(entries(&journal), vec![("b".into(), 4.into()), ("a".into(), 5.into()), ("b".into(), 1.into())]);
+
+        journal.compact().unwrap();
+        assert_eq!(entries(&journal), vec![("a".into(), 5.into()), ("b".into(), 1.into())]);
+
+        // replayed controls are not recorded again
+        let mut compiled = compile_journaled(&ops, &mesh, Box::new(empty()), journal).unwrap();
+        let out = compiled.subscribe_all();
+        compiled.shutdown();
+        block_on_all(out.collect()).unwrap();
+
+        assert_eq!(compiled.get(&add), Some(6.into()));
+        assert_eq!(Journal::open(&path).unwrap().entries().unwrap().len(), 2);
+
+        // plain compile starts over
+        let mut compiled = compile(&ops, &mesh, Box::new(empty())).unwrap();
+        let out = compiled.subscribe_all();
+        compiled.shutdown();
+        block_on_all(out.collect()).unwrap();
+
+        assert_eq!(compiled.get(&add), Some(3.into()));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_journal_ids() {
+        let ops = NodeDecls::new().with(basic_ops);
+        let mesh: Mesh = from_str(r#"{ "nodes": [], "ctrls": [{ "name": "a", "value": "1" }] }"#).unwrap();
+
+        let path = env::temp_dir().join(format!("linko-journal-ids-{}.jsonl", process::id()));
+        let _ = fs::remove_file(&path);
+
+        let compiled = compile_journaled(&ops, &mesh, Box::new(empty()), Journal::open(&path).unwrap()).unwrap();
+        assert_eq!(compiled.set_ctrl("a", 2), Ok(1));
+        assert_eq!(compiled.set_ctrl("a", 3), Ok(2));
+        drop(compiled);
+
+        let entries = Journal::open(&path).unwrap().entries().unwrap();
+        assert_eq!(entries.iter().map(|entry| entry.ctrl().id).collect::<Vec<_>>(), vec![1, 2]);
+        assert!(entries.iter().all(|entry| entry.ctrl().time > 0));
+
+        // restarted mesh continues numbering after journaled controls
+        let compiled = compile_journaled(&ops, &mesh, Box::new(empty()), Journal::open(&path).unwrap()).unwrap();
+        assert_eq!(compiled.set_ctrl("a", 4), Ok(3));
+        drop(compiled);
+
+        // entries of old journals have no IDs
+        let entry: JournalEntry = from_str(r#"{ "time": 1, "name": "a", "value": "5" }"#).unwrap();
+        assert_eq!(entry.ctrl(), InputControl { id: 0, time: 1, ..InputControl::new("a", 5) });
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_journal_failure() {
+        let mesh: Mesh = from_str(r#"{ "nodes": [], "ctrls": [{ "name": "a", "value": "1" }] }"#).unwrap();
+
+        // every write to this device fails
+        let journal = match Journal::open("/dev/full") {
+            Ok(journal) => journal,
+            Err(_) => return,
+        };
+
+        let mut intake = Intake::new(&mesh).with_journal(journal);
+
+        assert!(intake.push(InputControl::new("a", 2)).unwrap_err().starts_with("Unable to write `/dev/full`"));
+        assert_eq!(intake.push(InputControl::new("b", 2)), Err("Unknown control `b`".into()));
+    }
+}
@@ -0,0 +1,124 @@
+use std::fmt;
+use std::str::FromStr;
+
+use dsl::{Link, Value, ValuesMap};
+
+/// Textual form of links
+///
+/// Outputs are written as `node.out` and controls as `$ctrl`.
+/// The `.`, `$` and `\` characters in names are escaped by `\`, so `a\.b.=` is the output `=` of node `a.b`.
+impl fmt::Display for Link {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Link::Output { ref node, ref out } => {
+                write_escaped(f, node)?;
+                f.write_str(".")?;
+                write_escaped(f, out)
+            },
+            Link::Ctrl { ref name } => {
+                f.write_str("$")?;
+                write_escaped(f, name)
+            },
+        }
+    }
+}
+
+fn write_escaped(f: &mut fmt::Formatter, name: &str) -> fmt::Result {
+    for c in name.chars() {
+        if c == '.' || c == '$' || c == '\\' {
+            write!(f, "\\")?;
+        }
+        write!(f, "{}", c)?;
+    }
+    Ok(())
+}
+
+impl FromStr for Link {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let (is_ctrl, text) = if s.starts_with('$') { (true, &s[1..]) } else { (false, s) };
+
+        let mut names = vec![String::new()];
+        let mut chars = text.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some(c) => names.last_mut().unwrap().push(c),
+                    None => return Err(format!("Unterminated escape in link `{}`", s)),
+                },
+                '.' => names.push(String::new()),
+                '$' => return Err(format!("Unescaped `$` in link `{}`", s)),
+                c => names.last_mut().unwrap().push(c),
+            }
+        }
+
+        if names.iter().any(String::is_empty) {
+            return Err(format!("Empty name in link `{}`", s));
+        }
+
+        match (is_ctrl, names.len()) {
+            (true, 1) => Ok(Link::Ctrl { name: names.remove(0) }),
+            (false, 2) => {
+                let out = names.remove(1);
+                Ok(Link::Output { node: names.remove(0), out })
+            },
+            (true, _) => Err(format!("Unescaped `.` in control link `{}`", s)),
+            (false, _) => Err(format!("Expected `node.out` or `$ctrl` but found `{}`", s)),
+        }
+    }
+}
+
+/// Access to values by textual form of links
+pub trait ValuesQuery {
+    /// current value of link, if it is known
+    fn query<S: AsRef<str>>(&self, link: S) -> Result<Option<Value>, String>;
+}
+
+impl ValuesQuery for ValuesMap {
+    fn query<S: AsRef<str>>(&self, link: S) -> Result<Option<Value>, String> {
+        let link: Link = link.as_ref().parse()?;
+        Ok(self.get(&link).and_then(|value| *value.borrow()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::rc::{Rc};
+    use std::cell::{RefCell};
+    use super::{ValuesQuery};
+    use dsl::{Link, ValuesMap};
+
+    #[test]
+    fn test_link_text() {
+        assert_eq!(Link::output("add", "=").to_string(), "add.=");
+        assert_eq!(Link::ctrl("a").to_string(), "$a");
+        assert_eq!(Link::output("a.b", "$c").to_string(), "a\\.b.\\$c");
+        assert_eq!(Link::ctrl("x.y").to_string(), "$x\\.y");
+
+        for link in &[Link::output("add", "="), Link::ctrl("a"), Link::output("a.b", "$c\\"), Link::ctrl("x.y")] {
+            assert_eq!(&link.to_string().parse::<Link>().unwrap(), link);
+        }
+
+        assert_eq!("a\\.b.=".parse::<Link>(), Ok(Link::output("a.b", "=")));
+        assert!("add".parse::<Link>().is_err());
+        assert!("a.b.c".parse::<Link>().is_err());
+        assert!("$a.b".parse::<Link>().is_err());
+        assert!("a.".parse::<Link>().is_err());
+        assert!("$".parse::<Link>().is_err());
+        assert!("a\\".parse::<Link>().is_err());
+    }
+
+    #[test]
+    fn test_values_query() {
+        let mut values = ValuesMap::new();
+        values.insert(Rc::new(Link::output("add", "=")), Rc::new(RefCell::new(Some(7.into()))));
+        values.insert(Rc::new(Link::ctrl("a")), Rc::new(RefCell::new(None)));
+
+        assert_eq!(values.query("add.="), Ok(Some(7.into())));
+        assert_eq!(values.query("$a"), Ok(None));
+        assert_eq!(values.query("$b"), Ok(None));
+        assert!(values.query("add").is_err());
+    }
+}
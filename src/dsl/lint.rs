@@ -0,0 +1,345 @@
+use std::fmt;
+use std::collections::{HashSet};
+
+use dsl::{Mesh, Link, NodeDecls};
+
+/// Severity of lint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Severity {
+    /// Mesh works, but likely not as intended
+    Warning,
+    /// Mesh is ambiguous and cannot be compiled
+    Error,
+}
+
+/// Kind of lint with stable code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LintCode {
+    /// E001: several nodes with the same name
+    DuplicateNode,
+    /// E002: several controls with the same name
+    DuplicateCtrl,
+    /// E003: several inputs with the same name in node
+    DuplicateInput,
+    /// E004: several outputs with the same name in node
+    DuplicateOutput,
+    /// E005: several exports with the same name
+    DuplicateExport,
+    /// E006: input or export linked to missing node, output or control
+    DanglingLink,
+    /// W001: control which no one input is linked to
+    UnusedCtrl,
+    /// W002: node which outputs no one input or export is linked to
+    ///
+    /// Mesh without exports has results in outputs of such nodes, so it is not reported then.
+    UnusedNode,
+    /// W003: output declared by kind but not listed in node
+    UnlistedOutput,
+    /// W004: node which does not affect any export
+    UnexportedNode,
+}
+
+impl LintCode {
+    pub fn code(&self) -> &'static str {
+        use self::LintCode::*;
+        match *self {
+            DuplicateNode => "E001",
+            DuplicateCtrl => "E002",
+            DuplicateInput => "E003",
+            DuplicateOutput => "E004",
+            DuplicateExport => "E005",
+            DanglingLink => "E006",
+            UnusedCtrl => "W001",
+            UnusedNode => "W002",
+            UnlistedOutput => "W003",
+            UnexportedNode => "W004",
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        use self::LintCode::*;
+        match *self {
+            DuplicateNode | DuplicateCtrl | DuplicateInput | DuplicateOutput |
+            DuplicateExport | DanglingLink => Severity::Error,
+            UnusedCtrl | UnusedNode | UnlistedOutput | UnexportedNode => Severity::Warning,
+        }
+    }
+}
+
+impl fmt::Display for LintCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+/// Problem found in mesh
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lint {
+    pub code: LintCode,
+    pub message: String,
+}
+
+impl Lint {
+    pub fn new<S: Into<String>>(code: LintCode, message: S) -> Self {
+        Self { code, message: message.into() }
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.code.severity()
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity() == Severity::Error
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)
+    }
+}
+
+/// Check mesh for duplicate, unused and dangling elements
+///
+/// Declarations are used to find outputs of kinds which are not listed in nodes.
+pub fn lint(mesh: &Mesh, decls: Option<&NodeDecls>) -> Vec<Lint> {
+    let mut lints = Vec::new();
+
+    let mut node_names = HashSet::new();
+    for node in &mesh.nodes {
+        if !node_names.insert(node.name.as_str()) {
+            lints.push(Lint::new(LintCode::DuplicateNode, format!("Duplicate node `{}`", node.name)));
+        }
+
+        let mut in_names = HashSet::new();
+        for input in &node.ins {
+            if !in_names.insert(input.name.as_str()) {
+                lints.push(Lint::new(LintCode::DuplicateInput, format!("Duplicate input `{}` in node `{}`", input.name, node.name)));
+            }
+        }
+
+        let mut out_names = HashSet::new();
+        for output in &node.outs {
+            if !out_names.insert(output.name.as_str()) {
+                lints.push(Lint::new(LintCode::DuplicateOutput, format!("Duplicate output `{}` in node `{}`", output.name, node.name)));
+            }
+        }
+    }
+
+    let mut ctrl_names = HashSet::new();
+    for ctrl in &mesh.ctrls {
+        if !ctrl_names.insert(ctrl.name.as_str()) {
+            lints.push(Lint::new(LintCode::DuplicateCtrl, format!("Duplicate control `{}`", ctrl.name)));
+        }
+    }
+
+    let mut export_names = HashSet::new();
+    for export in &mesh.exports {
+        if !export_names.insert(export.name.as_str()) {
+            lints.push(Lint::new(LintCode::DuplicateExport, format!("Duplicate export `{}`", export.name)));
+        }
+    }
+
+    let index = mesh.index();
+    let dangling = |link: &Link| match *link {
+        Link::Output { node: ref link_node, out: ref link_out } => match index.get_node(link_node) {
+            Some(node) if node.get_out(link_out).is_some() => None,
+            Some(_) => Some(format!("missing output `{}` of node `{}`", link_out, link_node)),
+            None => Some(format!("missing node `{}`", link_node)),
+        },
+        Link::Ctrl { name: ref link_ctrl } => match index.get_ctrl(link_ctrl) {
+            Some(_) => None,
+            None => Some(format!("missing control `{}`", link_ctrl)),
+        },
+    };
+
+    for node in &mesh.nodes {
+        for input in &node.ins {
+            if let Some(target) = dangling(&input.link) {
+                lints.push(Lint::new(LintCode::DanglingLink, format!("Input `{}` of node `{}` linked to {}", input.name, node.name, target)));
+            }
+        }
+    }
+
+    for export in &mesh.exports {
+        if let Some(target) = dangling(&export.link) {
+            lints.push(Lint::new(LintCode::DanglingLink, format!("Export `{}` linked to {}", export.name, target)));
+        }
+    }
+
+    let mut used_ctrls = HashSet::new();
+    let mut used_nodes = HashSet::new();
+    let links = mesh.nodes.iter().flat_map(|node| node.ins.iter().map(|input| &input.link))
+        .chain(mesh.exports.iter().map(|export| &export.link));
+    for link in links {
+        match *link {
+            Link::Output { node: ref link_node, .. } => { used_nodes.insert(link_node.as_str()); },
+            Link::Ctrl { name: ref link_ctrl } => { used_ctrls.insert(link_ctrl.as_str()); },
+        }
+    }
+
+    for ctrl in &mesh.ctrls {
+        if !used_ctrls.contains(ctrl.name.as_str()) {
+            lints.push(Lint::new(LintCode::UnusedCtrl, format!("Control `{}` is never used", ctrl.name)));
+            // report once for duplicates
+            used_ctrls.insert(ctrl.name.as_str());
+        }
+    }
+
+    if !mesh.exports.is_empty() {
+        let mut unused_nodes = HashSet::new();
+        for node in &mesh.nodes {
+            if !used_nodes.contains(node.name.as_str()) && unused_nodes.insert(node.name.as_str()) {
+                lints.push(Lint::new(LintCode::UnusedNode, format!("Outputs of node `{}` are never used", node.name)));
+            }
+        }
+
+
+        let deps = mesh.upstream_all(mesh.exports.iter().map(|export| &export.link));
+        let exported: HashSet<_> = deps.nodes.iter().map(String::as_str).collect();
+        for node in &mesh.nodes {
+            // unused nodes are already reported
+            if !unused_nodes.contains(node.name.as_str()) && !exported.contains(node.name.as_str()) {
+                lints.push(Lint::new(LintCode::UnexportedNode, format!("Node `{}` does not affect any export", node.name)));
+            }
+        }
+    }
+
+    if let Some(decls) = decls {
+        for node in &mesh.nodes {
+            if let Some(decl) = decls.get(&node.kind) {
+                for output_kind in &decl.def.outs {
+                    if node.get_out(&output_kind.name).is_none() {
+                        lints.push(Lint::new(LintCode::UnlistedOutput, format!("Output `{}` of node `{}` is not listed", output_kind.name, node.name)));
+                    }
+                }
+            }
+        }
+    }
+
+    lints
+}
+
+#[cfg(test)]
+mod test {
+    use super::{lint, LintCode, Severity};
+    use dsl::{Mesh, NodeDecls};
+    use ops::{basic_ops};
+    use serde_json::{from_str};
+
+    #[test]
+    fn test_lint() {
+        let ops = NodeDecls::new().with(basic_ops);
+
+        let mesh: Mesh = from_str(r#"{
+  "nodes": [
+    { "name": "mul", "kind": "*", "ins": [
+      { "name": "a", "link": { "name": "a" } },
+      { "name": "b", "link": { "name": "a" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "add", "kind": "+", "ins": [
+      { "name": "a", "link": { "node": "mul", "out": "=" } },
+      { "name": "a", "link": { "name": "a" } }
+    ] },
+    { "name": "mul", "kind": "*", "ins": [], "outs": [] }
+  ],
+  "ctrls": [
+    { "name": "a", "value": "2" },
+    { "name": "b", "value": "3" },
+    { "name": "b", "value": "4" }
+  ]
+}"#).unwrap();
+
+        let lints = lint(&mesh, Some(&ops));
+
+        assert_eq!(lints.iter().map(|lint| lint.to_string()).collect::<Vec<_>>(), vec![
+            "E003: Duplicate input `a` in node `add`",
+            "E001: Duplicate node `mul`",
+            "E002: Duplicate control `b`",
+            "W001: Control `b` is never used",
+            "W003: Output `=` of node `add` is not listed",
+            "W003: Output `=` of node `mul` is not listed",
+        ]);
+
+        assert_eq!(lints[0].code, LintCode::DuplicateInput);
+        assert_eq!(lints[0].severity(), Severity::Error);
+        assert_eq!(lints[3].severity(), Severity::Warning);
+    }
+
+    #[test]
+    fn test_lint_exports() {
+        let mesh: Mesh = from_str(r#"{
+  "nodes": [
+    { "name": "neg", "kind": "-", "ins": [
+      { "name": "a", "link": { "name": "a" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "inv", "kind": "^-1", "ins": [
+      { "name": "a", "link": { "name": "a" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "mul", "kind": "*", "ins": [
+      { "name": "a", "link": { "node": "inv", "out": "=" } },
+      { "name": "b", "link": { "name": "a" } }
+    ], "outs": [
+      { "name": "=" }
+    ] }
+  ],
+  "ctrls": [
+    { "name": "a", "value": "2" }
+  ],
+  "exports": [
+    { "name": "neg", "link": { "node": "neg", "out": "=" } },
+    { "name": "neg", "link": { "name": "a" } }
+  ]
+}"#).unwrap();
+
+        assert_eq!(lint(&mesh, None).iter().map(|lint| lint.to_string()).collect::<Vec<_>>(), vec![
+            "E005: Duplicate export `neg`",
+            "W002: Outputs of node `mul` are never used",
+            "W004: Node `inv` does not affect any export",
+        ]);
+    }
+
+    #[test]
+    fn test_lint_dangling() {
+        let mesh: Mesh = from_str(r#"{
+  "nodes": [
+    { "name": "neg", "kind": "-", "ins": [
+      { "name": "a", "link": { "name": "b" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "add", "kind": "+", "ins": [
+      { "name": "a", "link": { "node": "neg", "out": "-" } },
+      { "name": "b", "link": { "node": "mul", "out": "=" } }
+    ], "outs": [
+      { "name": "=" }
+    ] }
+  ],
+  "ctrls": [
+    { "name": "a", "value": "2" }
+  ],
+  "exports": [
+    { "name": "sum", "link": { "node": "add", "out": "=" } },
+    { "name": "a", "link": { "name": "c" } }
+  ]
+}"#).unwrap();
+
+        let lints = lint(&mesh, None);
+
+        assert_eq!(lints.iter().map(|lint| lint.to_string()).collect::<Vec<_>>(), vec![
+            "E006: Input `a` of node `neg` linked to missing control `b`",
+            "E006: Input `a` of node `add` linked to missing output `-` of node `neg`",
+            "E006: Input `b` of node `add` linked to missing node `mul`",
+            "E006: Export `a` linked to missing control `c`",
+            "W001: Control `a` is never used",
+        ]);
+
+        assert!(lints[0].is_error());
+    }
+}
@@ -1,7 +1,57 @@
 mod def;
+mod link;
+pub mod value;
+mod shared;
 mod imp;
+mod index;
 mod compile;
+mod schema;
+mod format;
+mod render;
+mod graph;
+mod lint;
+mod optimize;
+mod pattern;
+mod compiled;
+mod intake;
+mod snapshot;
+mod journal;
+mod record;
+mod scenario;
+mod explain;
+mod symbolic;
+mod autodiff;
+mod seek;
+mod batch;
+mod sweep;
+mod interval;
+#[cfg(test)]
+pub mod testing;
 
 pub use self::def::*;
+pub use self::link::*;
+pub use self::value::{ValueStyle, parse_value};
+pub use self::shared::*;
 pub use self::imp::*;
+pub use self::index::*;
 pub use self::compile::*;
+pub use self::schema::*;
+pub use self::format::*;
+pub use self::render::*;
+pub use self::graph::*;
+pub use self::lint::*;
+pub use self::optimize::*;
+pub use self::pattern::*;
+pub use self::compiled::*;
+pub use self::intake::*;
+pub use self::snapshot::*;
+pub use self::journal::*;
+pub use self::record::*;
+pub use self::scenario::*;
+pub use self::explain::*;
+pub use self::symbolic::*;
+pub use self::autodiff::*;
+pub use self::seek::*;
+pub use self::batch::*;
+pub use self::sweep::*;
+pub use self::interval::*;
@@ -0,0 +1,302 @@
+use std::fmt;
+use std::collections::{HashMap, HashSet};
+
+use dsl::{Mesh, Node, Link, Ctrl, Value, NodeDecls, lint};
+
+/// Change made by optimization
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rewrite {
+    /// Output of node with constant inputs replaced by constant control
+    Fold {
+        link: Link,
+        ctrl: String,
+        value: Value,
+    },
+    /// Output of node replaced by equivalent link
+    Alias {
+        link: Link,
+        to: Link,
+        rule: &'static str,
+    },
+    /// Node removed because it does not affect exports anymore
+    Remove {
+        node: String,
+    },
+}
+
+impl fmt::Display for Rewrite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Rewrite::Fold { ref link, ref ctrl, ref value } =>
+                write!(f, "Folded `{}` into constant `{}` = {}", link, ctrl, value),
+            Rewrite::Alias { ref link, ref to, rule } =>
+                write!(f, "Replaced `{}` with `{}` ({})", link, to, rule),
+            Rewrite::Remove { ref node } =>
+                write!(f, "Removed node `{}`", node),
+        }
+    }
+}
+
+/// Simplify mesh
+///
+/// Outputs of pure nodes which inputs are constant are evaluated and replaced by constant controls.
+/// Identities declared by kinds are applied, like `-(-x) = x` or `x * 1 = x` of basic operations.
+/// Controls are treated as constant only when marked as `const`.
+///
+/// Nodes are removed only when the mesh declares exports, otherwise all outputs remain observable.
+pub fn optimize(decls: &NodeDecls, mesh: &Mesh) -> Result<(Mesh, Vec<Rewrite>), String> {
+    for lint in lint(mesh, Some(decls)) {
+        if lint.is_error() { return Err(lint.message); }
+    }
+
+    let order: Vec<_> = mesh.topo_order()?.into_iter().map(|node| node.name.clone()).collect();
+
+    let mut out = mesh.clone();
+    let index: HashMap<_, _> = mesh.nodes.iter().enumerate()
+        .map(|(i, node)| (node.name.clone(), i)).collect();
+
+    // replaced links
+    let mut subst: HashMap<Link, Link> = HashMap::new();
+    // links with known values
+    let mut consts: HashMap<Link, Value> = mesh.ctrls.iter()
+        .filter(|ctrl| ctrl.is_constant())
+        .map(|ctrl| (Link::ctrl(ctrl.name.clone()), ctrl.value))
+        .collect();
+    let mut ctrl_names: HashSet<_> = mesh.ctrls.iter().map(|ctrl| ctrl.name.clone()).collect();
+    let mut new_ctrls = Vec::new();
+    let mut rewrites = Vec::new();
+
+    for name in &order {
+        let i = index[name];
+
+        for input in &mut out.nodes[i].ins {
+            if let Some(link) = subst.get(&input.link) {
+                input.link = link.clone();
+            }
+        }
+
+        let node = &out.nodes[i];
+        let decl = match decls.get(&node.kind) {
+            Some(decl) if decl.def.pure => decl,
+            _ => continue,
+        };
+
+        let ins: Option<Vec<_>> = decl.def.ins.iter()
+            .map(|kind| node.get_in(&kind.name).and_then(|input| consts.get(&input.link).cloned()))
+            .collect();
+
+        if let Some(outs) = ins.and_then(|ins| decl.eval(&ins)) {
+            for (kind, value) in decl.def.outs.iter().zip(outs) {
+                if node.get_out(&kind.name).is_none() { continue; }
+
+                let link = Link::output(node.name.clone(), kind.name.clone());
+                let ctrl = unique_name(&mut ctrl_names, format!("{}.{}", node.name, kind.name));
+
+                consts.insert(Link::ctrl(ctrl.clone()), value);
+                subst.insert(link.clone(), Link::ctrl(ctrl.clone()));
+                new_ctrls.push(Ctrl::new(ctrl.clone(), value).with_constant());
+                rewrites.push(Rewrite::Fold { link, ctrl, value });
+            }
+            continue;
+        }
+
+        let site = NodeSite { node, nodes: &out.nodes, index: &index, consts: &consts, decls };
+        if let Some((out_name, to, rule)) = decl.identity(&site) {
+            let link = Link::output(node.name.clone(), out_name.into());
+            subst.insert(link.clone(), to.clone());
+            rewrites.push(Rewrite::Alias { link, to, rule });
+        }
+    }
+
+    for export in &mut out.exports {
+        if let Some(link) = subst.get(&export.link) {
+            export.link = link.clone();
+        }
+    }
+
+    // add only constants which are used
+    let used: HashSet<_> = out.nodes.iter()
+        .flat_map(|node| node.ins.iter().map(|input| &input.link))
+        .chain(out.exports.iter().map(|export| &export.link))
+        .cloned()
+        .collect();
+
+    out.ctrls.extend(new_ctrls.into_iter().filter(|ctrl| used.contains(&Link::ctrl(ctrl.name.clone()))));
+
+    if !out.exports.is_empty() {
+        let deps = out.upstream_all(out.exports.iter().map(|export| &export.link));
+        let alive: HashSet<_> = deps.nodes.into_iter().collect();
+
+        for node in &out.nodes {
+            if !alive.contains(&node.name) {
+                rewrites.push(Rewrite::Remove { node: node.name.clone() });
+            }
+        }
+
+        out.nodes.retain(|node| alive.contains(&node.name));
+    }
+
+    Ok((out, rewrites))
+}
+
+/// Node seen by identity rules of its kind
+pub struct NodeSite<'a> {
+    node: &'a Node,
+    nodes: &'a [Node],
+    index: &'a HashMap<String, usize>,
+    consts: &'a HashMap<Link, Value>,
+    decls: &'a NodeDecls,
+}
+
+impl<'a> NodeSite<'a> {
+    /// link of input
+    pub fn input(&self, name: &str) -> Option<&'a Link> {
+        self.node.get_in(name).map(|input| &input.link)
+    }
+
+    /// check that link is known to have value
+    pub fn is_const(&self, link: &Link, value: i32) -> bool {
+        self.consts.get(link).map(|v| *v == Value::from(value)).unwrap_or(false)
+    }
+
+    /// pure node which produces link and name of its output
+    pub fn source(&self, link: &Link) -> Option<(&'a Node, &'a str)> {
+        match *link {
+            Link::Output { node: ref name, ref out } => {
+                let node = &self.nodes[*self.index.get(name)?];
+                let out = node.get_out(out)?;
+                if self.decls.get(&node.kind).map(|decl| decl.def.pure).unwrap_or(false) {
+                    Some((node, out.name.as_str()))
+                } else {
+                    None
+                }
+            },
+            Link::Ctrl { .. } => None,
+        }
+    }
+}
+
+fn unique_name(names: &mut HashSet<String>, name: String) -> String {
+    let mut unique = name.clone();
+    let mut n = 1;
+    while names.contains(&unique) {
+        n += 1;
+        unique = format!("{}#{}", name, n);
+    }
+    names.insert(unique.clone());
+    unique
+}
+
+#[cfg(test)]
+mod test {
+    use super::{optimize};
+    use dsl::{Mesh, Link, NodeDecls};
+    use ops::{basic_ops};
+    use serde_json::{from_str};
+
+    #[test]
+    fn test_optimize_identities() {
+        let ops = NodeDecls::new().with(basic_ops);
+
+        let mesh: Mesh = from_str(r#"{
+  "nodes": [
+    { "name": "neg1", "kind": "-", "ins": [
+      { "name": "a", "link": { "name": "x" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "neg2", "kind": "-", "ins": [
+      { "name": "a", "link": { "node": "neg1", "out": "=" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "inv1", "kind": "^-1", "ins": [
+      { "name": "a", "link": { "name": "y" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "inv2", "kind": "^-1", "ins": [
+      { "name": "a", "link": { "node": "inv1", "out": "=" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "mul", "kind": "*", "ins": [
+      { "name": "a", "link": { "node": "neg2", "out": "=" } },
+      { "name": "b", "link": { "node": "inv2", "out": "=" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "one", "kind": "*", "ins": [
+      { "name": "a", "link": { "name": "one" } },
+      { "name": "b", "link": { "node": "mul", "out": "=" } }
+    ], "outs": [
+      { "name": "=" }
+    ] }
+  ],
+  "ctrls": [
+    { "name": "x", "value": "2" },
+    { "name": "y", "value": "3" },
+    { "name": "one", "value": "1", "const": true }
+  ],
+  "exports": [
+    { "name": "result", "link": { "node": "one", "out": "=" } }
+  ]
+}"#).unwrap();
+
+        let (mesh, rewrites) = optimize(&ops, &mesh).unwrap();
+
+        assert_eq!(rewrites.iter().map(|rewrite| rewrite.to_string()).collect::<Vec<_>>(), vec![
+            "Replaced `neg2.=` with `$x` (double negation)",
+            "Replaced `inv2.=` with `$y` (double inversion)",
+            "Replaced `one.=` with `mul.=` (multiplication by one)",
+            "Removed node `neg1`",
+            "Removed node `neg2`",
+            "Removed node `inv1`",
+            "Removed node `inv2`",
+            "Removed node `one`",
+        ]);
+
+        assert_eq!(mesh.nodes.iter().map(|node| node.name.as_str()).collect::<Vec<_>>(), vec!["mul"]);
+        assert_eq!(mesh.nodes[0].ins[0].link, Link::ctrl("x"));
+        assert_eq!(mesh.nodes[0].ins[1].link, Link::ctrl("y"));
+        assert_eq!(mesh.exports[0].link, Link::output("mul", "="));
+    }
+
+    #[test]
+    fn test_optimize_fold() {
+        let ops = NodeDecls::new().with(basic_ops);
+
+        let mesh: Mesh = from_str(r#"{
+  "nodes": [
+    { "name": "mul", "kind": "*", "ins": [
+      { "name": "a", "link": { "name": "a" } },
+      { "name": "b", "link": { "name": "b" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "add", "kind": "+", "ins": [
+      { "name": "a", "link": { "node": "mul", "out": "=" } },
+      { "name": "b", "link": { "name": "c" } }
+    ], "outs": [
+      { "name": "=" }
+    ] }
+  ],
+  "ctrls": [
+    { "name": "a", "value": "2", "const": true },
+    { "name": "b", "value": "3", "const": true },
+    { "name": "c", "value": "1" }
+  ]
+}"#).unwrap();
+
+        let (mesh, rewrites) = optimize(&ops, &mesh).unwrap();
+
+        assert_eq!(rewrites.iter().map(|rewrite| rewrite.to_string()).collect::<Vec<_>>(), vec![
+            "Folded `mul.=` into constant `mul.=` = 6",
+        ]);
+
+        // without exports all nodes remain observable
+        assert_eq!(mesh.nodes.len(), 2);
+        assert_eq!(mesh.nodes[1].ins[0].link, Link::ctrl("mul.="));
+        assert_eq!(mesh.get_ctrl("mul.=").map(|ctrl| (ctrl.value, ctrl.is_constant())), Some((6.into(), true)));
+    }
+}
@@ -0,0 +1,94 @@
+use std::fmt;
+
+use dsl::{Link};
+
+/// Pattern which selects links by textual form
+///
+/// Links are matched in textual form, like `node.out` for outputs and `$ctrl` for controls.
+/// The `*` matches any sequence of characters and the `?` matches any single character,
+/// so `node.*` selects all outputs of node and `$*` selects all controls.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LinkPattern {
+    glob: Vec<char>,
+}
+
+impl LinkPattern {
+    pub fn new<S: AsRef<str>>(glob: S) -> Result<Self, String> {
+        let glob = glob.as_ref();
+        if glob.is_empty() { return Err("Empty link pattern".into()); }
+        Ok(Self { glob: glob.chars().collect() })
+    }
+
+    /// pattern which matches all links
+    pub fn any() -> Self {
+        Self { glob: vec!['*'] }
+    }
+
+    pub fn matches(&self, link: &Link) -> bool {
+        let text: Vec<_> = link.to_string().chars().collect();
+        glob_match(&self.glob, &text)
+    }
+}
+
+impl fmt::Display for LinkPattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for c in &self.glob {
+            write!(f, "{}", c)?;
+        }
+        Ok(())
+    }
+}
+
+/// Match text with glob using backtracking on the last star
+fn glob_match(glob: &[char], text: &[char]) -> bool {
+    let (mut g, mut t) = (0, 0);
+    let mut star = None;
+
+    while t < text.len() {
+        if g < glob.len() && (glob[g] == '?' || glob[g] == text[t]) {
+            g += 1;
+            t += 1;
+        } else if g < glob.len() && glob[g] == '*' {
+            star = Some((g, t));
+            g += 1;
+        } else if let Some((star_g, star_t)) = star {
+            g = star_g + 1;
+            t = star_t + 1;
+            star = Some((star_g, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    glob[g..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod test {
+    use super::{LinkPattern};
+    use dsl::{Link};
+
+    #[test]
+    fn test_link_pattern() {
+        let add = Link::output("add", "=");
+        let mul = Link::output("mul", "=");
+        let ctrl = Link::ctrl("add");
+
+        let pattern = LinkPattern::new("add.*").unwrap();
+        assert!(pattern.matches(&add));
+        assert!(!pattern.matches(&mul));
+        assert!(!pattern.matches(&ctrl));
+
+        let pattern = LinkPattern::new("$*").unwrap();
+        assert!(pattern.matches(&ctrl));
+        assert!(!pattern.matches(&add));
+
+        let pattern = LinkPattern::new("?u?.=").unwrap();
+        assert!(pattern.matches(&mul));
+        assert!(!pattern.matches(&add));
+
+        assert!(LinkPattern::any().matches(&add));
+        assert!(LinkPattern::any().matches(&ctrl));
+        assert!(LinkPattern::new("").is_err());
+    }
+}
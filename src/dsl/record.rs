@@ -0,0 +1,295 @@
+use std::fmt;
+use std::rc::{Rc};
+use std::cell::{RefCell};
+use std::fs::{File};
+use std::io::{Read, Write};
+use std::path::{Path};
+use std::collections::{HashMap};
+
+use futures::{Stream};
+use futures::stream::{empty};
+use serde_json;
+
+use dsl::{Mesh, Link, Value, LinkValue, InputControl, OutputChange, ChangesStream, CompiledMesh, NodeDecls, compile, drain};
+
+/// Input or output of mesh
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Event {
+    /// Control received by mesh
+    Ctrl(InputControl),
+    /// Change produced by mesh
+    Change(LinkValue),
+}
+
+/// Event with sequence number
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub seq: u64,
+    pub event: Event,
+}
+
+/// Recorded session of mesh stored as JSON lines
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Recording {
+    pub records: Vec<Record>,
+}
+
+impl Recording {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let mut text = String::new();
+
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut text))
+            .map_err(|err| format!("Unable to read `{}`: {}", path.display(), err))?;
+
+        let records = text.lines().enumerate()
+            .filter(|&(_, line)| !line.trim().is_empty())
+            .map(|(number, line)| serde_json::from_str(line)
+                 .map_err(|err| format!("Invalid record at line {} of `{}`: {}", number + 1, path.display(), err)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { records })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        let mut text = String::new();
+
+        for record in &self.records {
+            text.push_str(&serde_json::to_string(record).map_err(|err| err.to_string())?);
+            text.push('\n');
+        }
+
+        File::create(path)
+            .and_then(|mut file| file.write_all(text.as_bytes()))
+            .map_err(|err| format!("Unable to write `{}`: {}", path.display(), err))
+    }
+
+    /// recorded controls in order of sequence
+    pub fn ctrls(&self) -> Vec<InputControl> {
+        self.events(|event| match *event {
+            Event::Ctrl(ref ctrl) => Some(ctrl.clone()),
+            _ => None,
+        })
+    }
+
+    /// recorded changes in order of sequence
+    pub fn changes(&self) -> Vec<LinkValue> {
+        self.events(|event| match *event {
+            Event::Change(ref change) => Some(change.clone()),
+            _ => None,
+        })
+    }
+
+    fn events<T, F: Fn(&Event) -> Option<T>>(&self, filter: F) -> Vec<T> {
+        let mut records: Vec<_> = self.records.iter().collect();
+        records.sort_by_key(|record| record.seq);
+        records.into_iter().filter_map(|record| filter(&record.event)).collect()
+    }
+}
+
+/// Captures inputs and outputs of mesh
+///
+/// Events are numbered in order they pass through recorder.
+#[derive(Clone, Default)]
+pub struct Recorder {
+    recording: Rc<RefCell<Recording>>,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn push(&self, event: Event) {
+        let mut recording = self.recording.borrow_mut();
+        let seq = recording.records.len() as u64;
+        recording.records.push(Record { seq, event });
+    }
+
+    /// record controls which mesh accepts, both from control stream and `set_ctrl`
+    ///
+    /// Controls are recorded with change IDs and times assigned by mesh.
+    pub fn ctrls(&self, compiled: &CompiledMesh) {
+        let recorder = self.clone();
+        compiled.observe_ctrls(move |ctrl| recorder.push(Event::Ctrl(ctrl.clone())));
+    }
+
+    /// record changes which pass from mesh
+    pub fn changes<S>(&self, changes: S) -> ChangesStream
+    where S: Stream<Item = OutputChange, Error = ()> + 'static
+    {
+        let recorder = self.clone();
+        Box::new(changes.map(move |change| {
+            recorder.push(Event::Change(LinkValue { link: (*change.link).clone(), value: change.value }));
+            change
+        }))
+    }
+
+    pub fn recording(&self) -> Recording {
+        self.recording.borrow().clone()
+    }
+}
+
+/// Difference between recorded and replayed changes of link
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangeDiff {
+    pub link: Link,
+    /// Position of change among changes of link
+    pub index: usize,
+    pub expected: Option<Value>,
+    pub actual: Option<Value>,
+}
+
+impl fmt::Display for ChangeDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} #{}: ", self.link, self.index)?;
+        match (self.expected, self.actual) {
+            (Some(expected), Some(actual)) => write!(f, "expected {} but found {}", expected, actual),
+            (Some(expected), None) => write!(f, "expected {} but found nothing", expected),
+            (None, Some(actual)) => write!(f, "unexpected {}", actual),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+/// Run mesh against recorded controls and compare changes with recorded ones
+///
+/// Controls are applied one at a time and changes caused by each of them are collected before the next one,
+/// so replay does not depend on how fast mesh was fed while recording.
+/// Changes are compared per link in order, since changes of different links may interleave differently.
+/// Only links which have recorded changes are compared.
+pub fn replay(decls: &NodeDecls, mesh: &Mesh, recording: &Recording) -> Result<Vec<ChangeDiff>, String> {
+    let mut expected: HashMap<Link, Vec<Value>> = HashMap::new();
+    for change in recording.changes() {
+        expected.entry(change.link).or_insert_with(Vec::new).push(change.value);
+    }
+
+    let links: Vec<_> = expected.keys().cloned().collect();
+
+    let compiled = compile(decls, mesh, Box::new(empty()))?;
+    let mut out = compiled.subscribe_links(&links);
+    let failed = |_| "Mesh failed while replaying".to_string();
+
+    let mut changes = drain(&mut out).map_err(failed)?;
+    for ctrl in recording.ctrls() {
+        compiled.set_ctrl(ctrl.name, ctrl.value)?;
+        changes.extend(drain(&mut out).map_err(failed)?);
+    }
+
+    let mut actual: HashMap<Link, Vec<Value>> = HashMap::new();
+    for change in changes {
+        actual.entry((*change.link).clone()).or_insert_with(Vec::new).push(change.value);
+    }
+
+    let mut diffs = Vec::new();
+    let empty = Vec::new();
+
+    for link in links {
+        let expected = &expected[&link];
+        let actual = actual.get(&link).unwrap_or(&empty);
+
+        for index in 0..expected.len().max(actual.len()) {
+            let (expected, actual) = (expected.get(index).cloned(), actual.get(index).cloned());
+            if expected != actual {
+                diffs.push(ChangeDiff { link: link.clone(), index, expected, actual });
+            }
+        }
+    }
+
+    diffs.sort_by(|a, b| (a.link.to_string(), a.index).cmp(&(b.link.to_string(), b.index)));
+
+    Ok(diffs)
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs;
+    use std::process;
+    use super::{Recorder, Recording, Event, replay};
+    use dsl::{Mesh, Value, Signal, InputControl, NodeKind, InputKind, OutputKind, NodeDecl, NodeDecls, Observables, compile, drain};
+    use ops::{basic_ops};
+    use serde_json::{from_str};
+    use futures::{Stream};
+    use futures::stream::{iter_ok};
+    use tokio::executor::current_thread::{block_on_all};
+
+    fn sub_impl(mut ins: Observables) -> Observables {
+        let (av, ai) = ins.get("a").into();
+        let (bv, bi) = ins.get("b").into();
+
+        let ro = Box::new(ai.map(|a| a.cause).select(bi.map(|b| b.cause)).map(move |cause| {
+            match (*av.borrow(), *bv.borrow()) {
+                (Some(a), Some(b)) => Some(Signal::new(a - b).with_cause(cause)),
+                _ => None,
+            }
+        }).skip_while(|opt| Ok(opt.is_none())).map(Option::unwrap));
+
+        Observables::new().put("=", ro)
+    }
+
+    #[test]
+    fn test_record_replay() {
+        let ops = NodeDecls::new().with(basic_ops);
+
+        let mesh: Mesh = from_str(r#"{
+  "nodes": [
+    { "name": "add", "kind": "+", "ins": [
+      { "name": "a", "link": { "name": "a" } },
+      { "name": "b", "link": { "name": "b" } }
+    ], "outs": [
+      { "name": "=" }
+    ] }
+  ],
+  "ctrls": [
+    { "name": "a", "value": "1" },
+    { "name": "b", "value": "0" }
+  ]
+}"#).unwrap();
+
+        let recorder = Recorder::new();
+
+        // controls come both from control stream and from handle
+        let mut compiled = compile(&ops, &mesh, Box::new(iter_ok(vec![InputControl::new("a", 3)]))).unwrap();
+        recorder.ctrls(&compiled);
+        let mut out = recorder.changes(compiled.subscribe_all());
+        drain(&mut out).unwrap();
+        compiled.set_ctrl("b", 2).unwrap();
+        drain(&mut out).unwrap();
+        compiled.shutdown();
+        block_on_all(out.collect()).unwrap();
+
+        let path = env::temp_dir().join(format!("linko-recording-{}.jsonl", process::id()));
+        recorder.recording().save(&path).unwrap();
+        let recording = Recording::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(recording, recorder.recording());
+        assert_eq!(recording.ctrls().iter().map(|ctrl| (ctrl.name.as_str(), ctrl.value, ctrl.id)).collect::<Vec<_>>(),
+                   vec![("a", 3.into(), 1), ("b", 2.into(), 2)]);
+        assert!(recording.records.iter().any(|record| match record.event {
+            Event::Change(ref change) => change.value == Value::from(5),
+            _ => false,
+        }));
+
+        assert_eq!(replay(&ops, &mesh, &recording), Ok(vec![]));
+
+        // upgraded implementation of `+` which is broken
+        let broken = NodeDecls::new().with(basic_ops)
+            .add(NodeDecl::new(
+                NodeKind::new("+")
+                    .with_in(InputKind::new("a"))
+                    .with_in(InputKind::new("b"))
+                    .with_out(OutputKind::new("=")),
+                sub_impl));
+
+        let diffs = replay(&broken, &mesh, &recording).unwrap();
+
+        assert!(!diffs.is_empty());
+        assert!(diffs.iter().all(|diff| diff.link.to_string() == "add.="));
+        assert_eq!(diffs.last().map(|diff| diff.to_string()), Some("add.= #2: expected 5 but found 1".into()));
+    }
+}
@@ -0,0 +1,260 @@
+use std::fmt::{Write};
+
+use dsl::{Mesh, Node, Input, Link, Value, NodeDecls, ValuesMap};
+
+/// Mesh visualization builder
+///
+/// Renders mesh as Graphviz DOT or Mermaid flowchart.
+/// Controls are shown as ellipses, nodes as boxes labelled with name and kind,
+/// edges are labelled with the output and the input they connect.
+pub struct MeshGraph<'a> {
+    mesh: &'a Mesh,
+    decls: Option<&'a NodeDecls>,
+    values: Option<&'a ValuesMap>,
+}
+
+impl<'a> MeshGraph<'a> {
+    pub fn new(mesh: &'a Mesh) -> Self {
+        Self { mesh, decls: None, values: None }
+    }
+
+    /// use kind descriptions from declarations
+    pub fn with_decls(mut self, decls: &'a NodeDecls) -> Self {
+        self.decls = Some(decls);
+        self
+    }
+
+    /// annotate edges with current values
+    pub fn with_values(mut self, values: &'a ValuesMap) -> Self {
+        self.values = Some(values);
+        self
+    }
+
+    /// render Graphviz DOT digraph
+    pub fn to_dot(&self) -> String {
+        let mut out = String::new();
+
+        out.push_str("digraph mesh {\n");
+        out.push_str("  rankdir=LR;\n");
+        out.push_str("  node [shape=box];\n");
+
+        for ctrl in &self.mesh.ctrls {
+            let _ = writeln!(out, "  {} [label={}, shape=ellipse];",
+                             dot_str(&ctrl_id(&ctrl.name)),
+                             dot_str(&format!("{} = {}", ctrl.name, self.ctrl_value(&ctrl.name, ctrl.value))));
+        }
+
+        for node in &self.mesh.nodes {
+            let _ = write!(out, "  {} [label={}", dot_str(&node_id(&node.name)),
+                           dot_str(&format!("{}\n{}", node.name, node.kind)));
+            if let Some(info) = self.node_info(node) {
+                let _ = write!(out, ", tooltip={}", dot_str(info));
+            }
+            out.push_str("];\n");
+        }
+
+        for (from, to, label) in self.edges() {
+            let _ = writeln!(out, "  {} -> {} [label={}];", dot_str(&from), dot_str(&to), dot_str(&label));
+        }
+
+        out.push_str("}\n");
+        out
+    }
+
+    /// render Mermaid flowchart
+    pub fn to_mermaid(&self) -> String {
+        let mut out = String::new();
+
+        out.push_str("flowchart LR\n");
+
+        for ctrl in &self.mesh.ctrls {
+            let _ = writeln!(out, "  {}([\"{}\"])", mermaid_id(&ctrl_id(&ctrl.name)),
+                             mermaid_str(&format!("{} = {}", ctrl.name, self.ctrl_value(&ctrl.name, ctrl.value))));
+        }
+
+        for node in &self.mesh.nodes {
+            let _ = writeln!(out, "  {}[\"{}<br/>{}\"]", mermaid_id(&node_id(&node.name)),
+                             mermaid_str(&node.name), mermaid_str(&node.kind));
+        }
+
+        for (from, to, label) in self.edges() {
+            let _ = writeln!(out, "  {} -->|\"{}\"| {}", mermaid_id(&from), mermaid_str(&label), mermaid_id(&to));
+        }
+
+        out
+    }
+
+    fn ctrl_value(&self, name: &str, initial: Value) -> Value {
+        self.value(&Link::ctrl(name)).unwrap_or(initial)
+    }
+
+    fn value(&self, link: &Link) -> Option<Value> {
+        self.values.and_then(|values| values.get(link)).and_then(|cell| *cell.borrow())
+    }
+
+    fn node_info(&self, node: &'a Node) -> Option<&'a str> {
+        if let Some(ref info) = node.info {
+            return Some(info.as_str());
+        }
+        self.decls.and_then(|decls| decls.get(&node.kind))
+            .and_then(|decl| decl.def.info.as_ref())
+            .map(String::as_str)
+    }
+
+    /// list of (source id, target id, label)
+    fn edges(&self) -> Vec<(String, String, String)> {
+        let mut edges = Vec::new();
+
+        for node in &self.mesh.nodes {
+            for input in self.ordered_inputs(node) {
+                let (from, label) = match input.link {
+                    Link::Output { node: ref link_node, out: ref link_out } => {
+                        if self.mesh.get_node(link_node).is_none() { continue; }
+                        (node_id(link_node), format!("{} -> {}", link_out, input.name))
+                    },
+                    Link::Ctrl { name: ref link_ctrl } => {
+                        if self.mesh.get_ctrl(link_ctrl).is_none() { continue; }
+                        (ctrl_id(link_ctrl), input.name.clone())
+                    },
+                };
+                let label = match self.value(&input.link) {
+                    Some(value) => format!("{} ({})", label, value),
+                    None => label,
+                };
+                edges.push((from, node_id(&node.name), label));
+            }
+        }
+
+        edges
+    }
+
+    /// inputs in the order of kind declaration when it is known
+    fn ordered_inputs(&self, node: &'a Node) -> Vec<&'a Input> {
+        let mut inputs: Vec<_> = node.ins.iter().collect();
+
+        if let Some(decl) = self.decls.and_then(|decls| decls.get(&node.kind)) {
+            let order = |name: &str| decl.def.ins.iter().position(|kind| kind.name == name);
+            inputs.sort_by_key(|input| order(&input.name));
+        }
+
+        inputs
+    }
+}
+
+fn ctrl_id(name: &str) -> String {
+    format!("ctrl:{}", name)
+}
+
+fn node_id(name: &str) -> String {
+    format!("node:{}", name)
+}
+
+fn dot_str(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            _ => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Mermaid ids may contain only alphanumerics and underscores
+fn mermaid_id(id: &str) -> String {
+    let mut out = String::with_capacity(id.len());
+    for c in id.chars() {
+        if c.is_ascii_alphanumeric() {
+            out.push(c);
+        } else if c == ':' {
+            out.push_str("__");
+        } else {
+            let _ = write!(out, "_{:x}_", c as u32);
+        }
+    }
+    out
+}
+
+fn mermaid_str(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("#quot;"),
+            '<' => out.push_str("#lt;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use std::rc::{Rc};
+    use std::cell::{RefCell};
+    use super::{MeshGraph};
+    use dsl::{Mesh, Link, ValuesMap};
+    use serde_json::{from_str};
+
+    fn sample_mesh() -> Mesh {
+        from_str(r#"{
+  "nodes": [
+    { "name": "mul", "kind": "*", "info": "product", "ins": [
+      { "name": "b", "link": { "name": "b" } },
+      { "name": "a", "link": { "name": "a" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "neg", "kind": "-", "ins": [
+      { "name": "a", "link": { "node": "mul", "out": "=" } }
+    ], "outs": [
+      { "name": "=" }
+    ] }
+  ],
+  "ctrls": [
+    { "name": "a", "value": "2" },
+    { "name": "b", "value": "3" }
+  ]
+}"#).unwrap()
+    }
+
+    #[test]
+    fn test_render_dot() {
+        let mesh = sample_mesh();
+
+        assert_eq!(MeshGraph::new(&mesh).to_dot(), r#"digraph mesh {
+  rankdir=LR;
+  node [shape=box];
+  "ctrl:a" [label="a = 2", shape=ellipse];
+  "ctrl:b" [label="b = 3", shape=ellipse];
+  "node:mul" [label="mul\n*", tooltip="product"];
+  "node:neg" [label="neg\n-"];
+  "ctrl:b" -> "node:mul" [label="b"];
+  "ctrl:a" -> "node:mul" [label="a"];
+  "node:mul" -> "node:neg" [label="= -> a"];
+}
+"#);
+    }
+
+    #[test]
+    fn test_render_mermaid_with_values() {
+        let mesh = sample_mesh();
+        let mut values = ValuesMap::new();
+
+        values.insert(Rc::new(Link::ctrl("a")), Rc::new(RefCell::new(Some(5.into()))));
+        values.insert(Rc::new(Link::output("mul", "=")), Rc::new(RefCell::new(Some(15.into()))));
+
+        assert_eq!(MeshGraph::new(&mesh).with_values(&values).to_mermaid(), r#"flowchart LR
+  ctrl__a(["a = 5"])
+  ctrl__b(["b = 3"])
+  node__mul["mul<br/>*"]
+  node__neg["neg<br/>-"]
+  ctrl__b -->|"b"| node__mul
+  ctrl__a -->|"a (5)"| node__mul
+  node__mul -->|"= -> a (15)"| node__neg
+"#);
+    }
+}
@@ -0,0 +1,254 @@
+use std::fmt;
+use std::fs::{File};
+use std::io::{Read};
+use std::path::{Path};
+use std::collections::{HashMap};
+
+use futures::stream::{empty};
+
+use dsl::{Mesh, Link, Value, InputControl, NodeDecls, Format, CompiledMesh, compile, drain};
+
+/// Mesh which scenario runs against
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MeshSource {
+    /// Path to mesh document, relative to scenario file
+    Path(String),
+    /// Mesh itself
+    Inline(Mesh),
+}
+
+/// Expected value of link
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Expect {
+    /// Link in textual form like `node.out` or `$ctrl`, or name of export
+    pub link: String,
+    #[serde(with = "::dsl::value")]
+    pub value: Value,
+}
+
+/// Controls to set and values to check after that
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Step {
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub info: Option<String>,
+    #[serde(default)]
+    pub ctrls: Vec<InputControl>,
+    #[serde(default)]
+    pub expect: Vec<Expect>,
+}
+
+/// Test of mesh behavior described as data
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Scenario {
+    pub mesh: MeshSource,
+    /// Maximum absolute difference between expected and actual values
+    #[serde(with = "::dsl::value", default = "no_tolerance", skip_serializing_if = "Value::is_zero")]
+    pub tolerance: Value,
+    pub steps: Vec<Step>,
+}
+
+fn no_tolerance() -> Value {
+    Value::from(0)
+}
+
+/// Value which does not match expectation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mismatch {
+    /// Number of step starting from one
+    pub step: usize,
+    pub link: String,
+    pub expected: Value,
+    pub actual: Option<Value>,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Step {}: `{}` expected {} but found ", self.step, self.link, self.expected)?;
+        match self.actual {
+            Some(actual) => write!(f, "{}", actual),
+            None => f.write_str("nothing"),
+        }
+    }
+}
+
+impl Scenario {
+    /// Load scenario from file
+    ///
+    /// The format is determined by file extension, the path of mesh is resolved relative to scenario.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let mut data = Vec::new();
+
+        File::open(path)
+            .and_then(|mut file| file.read_to_end(&mut data))
+            .map_err(|err| format!("Unable to read `{}`: {}", path.display(), err))?;
+
+        let format = Format::from_path(path).or_else(|| Format::detect(&data))
+            .ok_or_else(|| format!("Unknown scenario format of `{}`", path.display()))?;
+
+        let mut scenario: Self = format.deserialize(&data)?;
+
+        if let MeshSource::Path(ref mut mesh_path) = scenario.mesh {
+            if let Some(dir) = path.parent() {
+                *mesh_path = dir.join(&mesh_path).to_string_lossy().into_owned();
+            }
+        }
+
+        Ok(scenario)
+    }
+
+    pub fn mesh(&self) -> Result<Mesh, String> {
+        match self.mesh {
+            MeshSource::Path(ref path) => Mesh::load(path),
+            MeshSource::Inline(ref mesh) => Ok(mesh.clone()),
+        }
+    }
+
+    /// Run steps and collect values which do not match expectations
+    ///
+    /// Mesh is compiled once, controls of each step are applied to it
+    /// and all changes caused by them are collected before expectations are checked.
+    pub fn run(&self, decls: &NodeDecls) -> Result<Vec<Mismatch>, String> {
+        let mesh = self.mesh()?;
+        let compiled = compile(decls, &mesh, Box::new(empty()))?;
+        let mut out = compiled.subscribe_all();
+        let failed = |_| "Mesh failed while running scenario".to_string();
+        let mut mismatches = Vec::new();
+
+        drain(&mut out).map_err(failed)?;
+
+        for (i, step) in self.steps.iter().enumerate() {
+            let number = i + 1;
+
+            for ctrl in &step.ctrls {
+                if mesh.get_ctrl(&ctrl.name).is_none() {
+                    return Err(format!("Step {}: unknown control `{}`", number, ctrl.name));
+                }
+                compiled.set_ctrl(&ctrl.name, ctrl.value).map_err(|err| format!("Step {}: {}", number, err))?;
+            }
+
+            drain(&mut out).map_err(failed)?;
+            let values = link_values(&compiled)?;
+
+            for expect in &step.expect {
+                let link = mesh.resolve_link(&expect.link)
+                    .map_err(|err| format!("Step {}: {}", number, err))?;
+                let actual = values.get(&link).cloned();
+
+                if !actual.map(|actual| (actual - expect.value).abs() <= self.tolerance).unwrap_or(false) {
+                    mismatches.push(Mismatch { step: number, link: expect.link.clone(), expected: expect.value, actual });
+                }
+            }
+        }
+
+        Ok(mismatches)
+    }
+}
+
+/// Current values of all links
+fn link_values(compiled: &CompiledMesh) -> Result<HashMap<Link, Value>, String> {
+    Ok(compiled.save_state()?.values.into_iter().map(|value| (value.link, value.value)).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::{Write};
+    use std::process;
+    use super::{Scenario};
+    use dsl::{NodeDecls};
+    use ops::{basic_ops};
+    use serde_json::{from_str};
+
+    #[test]
+    fn test_scenario() {
+        let ops = NodeDecls::new().with(basic_ops);
+
+        let scenario: Scenario = from_str(r#"{
+  "mesh": {
+    "nodes": [
+      { "name": "mul", "kind": "*", "ins": [
+        { "name": "a", "link": { "name": "a" } },
+        { "name": "b", "link": { "name": "b" } }
+      ], "outs": [
+        { "name": "=" }
+      ] },
+      { "name": "inv", "kind": "^-1", "ins": [
+        { "name": "a", "link": { "node": "mul", "out": "=" } }
+      ], "outs": [
+        { "name": "=" }
+      ] }
+    ],
+    "ctrls": [
+      { "name": "a", "value": "1" },
+      { "name": "b", "value": "3" }
+    ],
+    "exports": [
+      { "name": "result", "link": { "node": "inv", "out": "=" } }
+    ]
+  },
+  "tolerance": "0.001",
+  "steps": [
+    { "expect": [
+      { "link": "result", "value": "0.333" }
+    ] },
+    { "ctrls": [
+      { "name": "a", "value": "2" }
+    ], "expect": [
+      { "link": "mul.=", "value": "6" },
+      { "link": "result", "value": "0.2" }
+    ] }
+  ]
+}"#).unwrap();
+
+        let mismatches = scenario.run(&ops).unwrap();
+
+        assert_eq!(mismatches.iter().map(|mismatch| mismatch.to_string()).collect::<Vec<_>>(), vec![
+            "Step 2: `result` expected 0.2 but found 0.1666666666666666666666666666666667",
+        ]);
+    }
+
+    #[test]
+    fn test_scenario_load() {
+        let ops = NodeDecls::new().with(basic_ops);
+        let dir = env::temp_dir().join(format!("linko-scenario-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        File::create(dir.join("neg.json")).unwrap().write_all(br#"{
+  "nodes": [
+    { "name": "neg", "kind": "-", "ins": [
+      { "name": "a", "link": { "name": "x" } }
+    ], "outs": [
+      { "name": "=" }
+    ] }
+  ],
+  "ctrls": [
+    { "name": "x", "value": "1" }
+  ]
+}"#).unwrap();
+
+        File::create(dir.join("neg.yaml")).unwrap().write_all(br#"
+mesh: neg.json
+steps:
+  - ctrls:
+      - name: x
+        value: "5"
+    expect:
+      - link: neg.=
+        value: "-5"
+  - ctrls:
+      - name: y
+        value: "1"
+"#).unwrap();
+
+        let scenario = Scenario::load(dir.join("neg.yaml")).unwrap();
+        let res = scenario.run(&ops);
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(res, Err("Step 2: unknown control `y`".into()));
+    }
+}
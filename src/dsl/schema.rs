@@ -0,0 +1,314 @@
+use serde_json::{Value as Json, Map};
+
+use dsl::{NodeKind, NodeDecls};
+
+const SCHEMA_DRAFT: &str = "http://json-schema.org/draft-07/schema#";
+
+/// Decimal number in textual form as accepted by `d128`
+const DECIMAL_PATTERN: &str = r"^[+-]?([0-9]+(\.[0-9]*)?|\.[0-9]+)([eE][+-]?[0-9]+)?$";
+
+/// JSON Schema document for `Link`
+pub fn link_schema() -> Json {
+    document("Link", definitions(node_schema_def()))
+}
+
+/// JSON Schema document for `Ctrl`
+pub fn ctrl_schema() -> Json {
+    document("Ctrl", definitions(node_schema_def()))
+}
+
+/// JSON Schema document for `Node`
+pub fn node_schema() -> Json {
+    document("Node", definitions(node_schema_def()))
+}
+
+/// JSON Schema document for `NodeKind`
+pub fn node_kind_schema() -> Json {
+    document("NodeKind", definitions(node_schema_def()))
+}
+
+/// JSON Schema document for `Mesh`
+pub fn mesh_schema() -> Json {
+    document("Mesh", definitions(node_schema_def()))
+}
+
+/// JSON Schema document for `Mesh` which accepts only nodes of kinds declared in registry
+///
+/// Each node must have one of registered kinds, its inputs must be named
+/// after declared inputs of that kind (all of them must be present)
+/// and its outputs must be named after declared outputs.
+pub fn mesh_schema_for(decls: &NodeDecls) -> Json {
+    let mut kinds: Vec<_> = decls.iter().map(|decl| &decl.def).collect();
+    kinds.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let node = if kinds.is_empty() {
+        json!(false)
+    } else {
+        json!({ "oneOf": kinds.iter().map(|kind| node_of_kind_def(kind)).collect::<Vec<_>>() })
+    };
+
+    document("Mesh", definitions(node))
+}
+
+fn document(root: &str, defs: Map<String, Json>) -> Json {
+    json!({
+        "$schema": SCHEMA_DRAFT,
+        "title": root,
+        "allOf": [{ "$ref": format!("#/definitions/{}", root) }],
+        "definitions": defs
+    })
+}
+
+fn definitions(node: Json) -> Map<String, Json> {
+    let mut defs = Map::new();
+
+    defs.insert("Name".into(), json!({
+        "type": "string",
+        "minLength": 1
+    }));
+
+    defs.insert("Info".into(), json!({
+        "type": "string"
+    }));
+
+    defs.insert("Value".into(), json!({
+        "oneOf": [
+            { "type": "string", "pattern": DECIMAL_PATTERN },
+            { "type": "number" }
+        ]
+    }));
+
+    defs.insert("Link".into(), json!({
+        "oneOf": [
+            {
+                "type": "object",
+                "description": "Link to node output",
+                "properties": {
+                    "node": { "$ref": "#/definitions/Name" },
+                    "out": { "$ref": "#/definitions/Name" }
+                },
+                "required": ["node", "out"],
+                "additionalProperties": false
+            },
+            {
+                "type": "object",
+                "description": "Link to control",
+                "properties": {
+                    "name": { "$ref": "#/definitions/Name" }
+                },
+                "required": ["name"],
+                "additionalProperties": false
+            }
+        ]
+    }));
+
+    defs.insert("Ctrl".into(), json!({
+        "type": "object",
+        "properties": {
+            "name": { "$ref": "#/definitions/Name" },
+            "value": { "$ref": "#/definitions/Value" },
+            "const": { "type": "boolean" }
+        },
+        "required": ["name", "value"],
+        "additionalProperties": false
+    }));
+
+    defs.insert("Input".into(), json!({
+        "type": "object",
+        "properties": {
+            "name": { "$ref": "#/definitions/Name" },
+            "info": { "$ref": "#/definitions/Info" },
+            "link": { "$ref": "#/definitions/Link" }
+        },
+        "required": ["name", "link"],
+        "additionalProperties": false
+    }));
+
+    defs.insert("Output".into(), json!({
+        "type": "object",
+        "properties": {
+            "name": { "$ref": "#/definitions/Name" },
+            "info": { "$ref": "#/definitions/Info" }
+        },
+        "required": ["name"],
+        "additionalProperties": false
+    }));
+
+    defs.insert("InputKind".into(), json!({
+        "type": "object",
+        "properties": {
+            "name": { "$ref": "#/definitions/Name" },
+            "info": { "$ref": "#/definitions/Info" }
+        },
+        "required": ["name"],
+        "additionalProperties": false
+    }));
+
+    defs.insert("OutputKind".into(), json!({
+        "type": "object",
+        "properties": {
+            "name": { "$ref": "#/definitions/Name" },
+            "info": { "$ref": "#/definitions/Info" }
+        },
+        "required": ["name"],
+        "additionalProperties": false
+    }));
+
+    defs.insert("NodeKind".into(), json!({
+        "type": "object",
+        "properties": {
+            "name": { "$ref": "#/definitions/Name" },
+            "info": { "$ref": "#/definitions/Info" },
+            "ins": { "type": "array", "items": { "$ref": "#/definitions/InputKind" } },
+            "outs": { "type": "array", "items": { "$ref": "#/definitions/OutputKind" } },
+            "pure": { "type": "boolean" }
+        },
+        "required": ["name"],
+        "additionalProperties": false
+    }));
+
+    defs.insert("Export".into(), json!({
+        "type": "object",
+        "properties": {
+            "name": { "$ref": "#/definitions/Name" },
+            "info": { "$ref": "#/definitions/Info" },
+            "link": { "$ref": "#/definitions/Link" }
+        },
+        "required": ["name", "link"],
+        "additionalProperties": false
+    }));
+
+    defs.insert("Node".into(), node);
+
+    defs.insert("Mesh".into(), json!({
+        "type": "object",
+        "properties": {
+            "nodes": { "type": "array", "items": { "$ref": "#/definitions/Node" } },
+            "ctrls": { "type": "array", "items": { "$ref": "#/definitions/Ctrl" } },
+            "exports": { "type": "array", "items": { "$ref": "#/definitions/Export" } }
+        },
+        "required": ["nodes", "ctrls"],
+        "additionalProperties": false
+    }));
+
+    defs
+}
+
+fn node_schema_def() -> Json {
+    json!({
+        "type": "object",
+        "properties": {
+            "kind": { "$ref": "#/definitions/Name" },
+            "name": { "$ref": "#/definitions/Name" },
+            "info": { "$ref": "#/definitions/Info" },
+            "ins": { "type": "array", "items": { "$ref": "#/definitions/Input" } },
+            "outs": { "type": "array", "items": { "$ref": "#/definitions/Output" } }
+        },
+        "required": ["kind", "name"],
+        "additionalProperties": false
+    })
+}
+
+fn node_of_kind_def(kind: &NodeKind) -> Json {
+    let in_names: Vec<_> = kind.ins.iter().map(|input| input.name.as_str()).collect();
+    let out_names: Vec<_> = kind.outs.iter().map(|output| output.name.as_str()).collect();
+
+    let mut node = json!({
+        "type": "object",
+        "properties": {
+            "kind": { "const": kind.name },
+            "name": { "$ref": "#/definitions/Name" },
+            "info": { "$ref": "#/definitions/Info" },
+            "ins": {
+                "type": "array",
+                "items": {
+                    "allOf": [
+                        { "$ref": "#/definitions/Input" },
+                        { "properties": { "name": { "enum": in_names } } }
+                    ]
+                },
+                "allOf": in_names.iter().map(|name| json!({
+                    "contains": { "properties": { "name": { "const": name } } }
+                })).collect::<Vec<_>>()
+            },
+            "outs": {
+                "type": "array",
+                "items": {
+                    "allOf": [
+                        { "$ref": "#/definitions/Output" },
+                        { "properties": { "name": { "enum": out_names } } }
+                    ]
+                }
+            }
+        },
+        "required": ["kind", "name"],
+        "additionalProperties": false
+    });
+
+    if let Some(ref info) = kind.info {
+        node["description"] = info.as_str().into();
+    }
+
+    if !in_names.is_empty() {
+        node["required"] = json!(["kind", "name", "ins"]);
+    } else {
+        node["properties"]["ins"]["maxItems"] = 0.into();
+    }
+
+    if out_names.is_empty() {
+        node["properties"]["outs"]["maxItems"] = 0.into();
+    }
+
+    node
+}
+
+#[cfg(test)]
+mod test {
+    use super::{mesh_schema, mesh_schema_for, link_schema};
+    use dsl::{NodeDecls};
+    use ops::{basic_ops};
+
+    #[test]
+    fn test_mesh_schema() {
+        let schema = mesh_schema();
+
+        assert_eq!(schema["title"], json!("Mesh"));
+        assert_eq!(schema["allOf"][0]["$ref"], json!("#/definitions/Mesh"));
+
+        let defs = &schema["definitions"];
+
+        assert_eq!(defs["Mesh"]["required"], json!(["nodes", "ctrls"]));
+        assert_eq!(defs["Mesh"]["properties"]["nodes"]["items"]["$ref"], json!("#/definitions/Node"));
+        assert_eq!(defs["Node"]["properties"]["kind"]["$ref"], json!("#/definitions/Name"));
+    }
+
+    #[test]
+    fn test_link_schema() {
+        let schema = link_schema();
+
+        assert_eq!(schema["allOf"][0]["$ref"], json!("#/definitions/Link"));
+
+        let link = &schema["definitions"]["Link"];
+
+        assert_eq!(link["oneOf"][0]["required"], json!(["node", "out"]));
+        assert_eq!(link["oneOf"][1]["required"], json!(["name"]));
+    }
+
+    #[test]
+    fn test_mesh_schema_for_decls() {
+        let ops = NodeDecls::new().with(basic_ops);
+        let schema = mesh_schema_for(&ops);
+
+        let kinds = schema["definitions"]["Node"]["oneOf"].as_array().unwrap();
+
+        assert_eq!(kinds.iter().map(|kind| kind["properties"]["kind"]["const"].clone()).collect::<Vec<_>>(),
+                   vec![json!("*"), json!("+"), json!("-"), json!("^-1")]);
+
+        let mul = &kinds[0];
+
+        assert_eq!(mul["properties"]["ins"]["items"]["allOf"][1]["properties"]["name"]["enum"], json!(["a", "b"]));
+        assert_eq!(mul["properties"]["ins"]["allOf"][1]["contains"]["properties"]["name"]["const"], json!("b"));
+        assert_eq!(mul["properties"]["outs"]["items"]["allOf"][1]["properties"]["name"]["enum"], json!(["="]));
+        assert_eq!(mul["required"], json!(["kind", "name", "ins"]));
+    }
+}
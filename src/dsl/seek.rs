@@ -0,0 +1,241 @@
+use std::fmt;
+use std::collections::{HashMap};
+
+use dsl::{Mesh, Link, Value, NodeDecls, CompiledMesh, Evaluator};
+
+/// Search of control value which makes output reach target
+///
+/// Bracketing search: secant steps are taken while they shrink the bracket fast enough,
+/// bisection steps otherwise, so the search always converges once target is bracketed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GoalSeek {
+    pub ctrl: String,
+    pub output: Link,
+    pub target: Value,
+    pub low: Value,
+    pub high: Value,
+    /// Acceptable distance between output and target
+    pub tolerance: Value,
+    pub max_iters: usize,
+}
+
+/// Result of goal seek with convergence diagnostics
+#[derive(Debug, Clone, PartialEq)]
+pub struct Solution {
+    /// Found value of control
+    pub value: Value,
+    /// Value of output at found value of control
+    pub output: Value,
+    /// Difference between output and target
+    pub residual: Value,
+    pub iters: usize,
+    /// Whether residual is within tolerance
+    pub converged: bool,
+    /// Final bracket of control value
+    pub bracket: (Value, Value),
+}
+
+impl GoalSeek {
+    pub fn new<S: Into<String>>(ctrl: S, output: Link, target: Value, low: Value, high: Value) -> Self {
+        Self {
+            ctrl: ctrl.into(), output, target, low, high,
+            tolerance: "0.000000000001".parse().unwrap(),
+            max_iters: 100,
+        }
+    }
+
+    pub fn with_tolerance(mut self, tolerance: Value) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn with_max_iters(mut self, max_iters: usize) -> Self {
+        self.max_iters = max_iters;
+        self
+    }
+
+    /// Search starting from values of controls in `point`, missing ones take values from mesh
+    pub fn solve(&self, decls: &NodeDecls, mesh: &Mesh, point: &HashMap<String, Value>) -> Result<Solution, String> {
+        match mesh.get_ctrl(&self.ctrl) {
+            None => return Err(format!("Unknown control `{}`", self.ctrl)),
+            Some(ctrl) if ctrl.is_constant() => return Err(format!("Control `{}` is constant", self.ctrl)),
+            _ => (),
+        }
+
+        if !(self.low < self.high) {
+            return Err(format!("Invalid bounds [{}, {}]", self.low, self.high));
+        }
+
+        let evaluator = Evaluator::new(decls, mesh, &[self.output.clone()])?;
+        let mut point = point.clone();
+        let mut residual = |value: Value| -> Result<Value, String> {
+            point.insert(self.ctrl.clone(), value);
+            let output = evaluator.eval(&point)[0];
+            if output.is_nan() {
+                return Err(format!("Output `{}` is not a number when `{}` = {}", self.output, self.ctrl, value));
+            }
+            Ok(output - self.target)
+        };
+
+        let (mut a, mut b) = (self.low, self.high);
+        let (mut fa, mut fb) = (residual(a)?, residual(b)?);
+
+        let solution = |value: Value, residual: Value, iters: usize, bracket: (Value, Value)| Solution {
+            value, residual, iters, bracket,
+            output: residual + self.target,
+            converged: residual.abs() <= self.tolerance,
+        };
+
+        if fa.abs() <= self.tolerance { return Ok(solution(a, fa, 0, (a, b))); }
+        if fb.abs() <= self.tolerance { return Ok(solution(b, fb, 0, (a, b))); }
+
+        if fa.is_negative() == fb.is_negative() {
+            return Err(format!("Target {} of `{}` is not bracketed by `{}` in [{}, {}]: output is {} and {}",
+                               self.target, self.output, self.ctrl, a, b, fa + self.target, fb + self.target));
+        }
+
+        let two = Value::from(2);
+        let mut bisect = false;
+
+        for iter in 1..self.max_iters + 1 {
+            let width = b - a;
+            let mid = (a + b) / two;
+            let x = if bisect { mid } else {
+                let x = b - fb * width / (fb - fa);
+                if a < x && x < b { x } else { mid }
+            };
+
+            // bracket can not shrink within precision
+            if !(a < x && x < b) {
+                return Ok(if fa.abs() < fb.abs() { solution(a, fa, iter, (a, b)) } else { solution(b, fb, iter, (a, b)) });
+            }
+
+            let fx = residual(x)?;
+
+            if fx.abs() <= self.tolerance {
+                return Ok(solution(x, fx, iter, (a, b)));
+            }
+
+            if fx.is_negative() == fa.is_negative() {
+                a = x;
+                fa = fx;
+            } else {
+                b = x;
+                fb = fx;
+            }
+
+            // fall back to bisection when secant step was too short
+            bisect = !bisect && (b - a) * two > width;
+        }
+
+        let iters = self.max_iters;
+        Ok(if fa.abs() < fb.abs() { solution(a, fa, iters, (a, b)) } else { solution(b, fb, iters, (a, b)) })
+    }
+}
+
+impl fmt::Display for Solution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} gives {} (residual {}) after {} iterations",
+               self.value, self.output, self.residual, self.iters)?;
+        if !self.converged {
+            write!(f, ", not converged within [{}, {}]", self.bracket.0, self.bracket.1)?;
+        }
+        Ok(())
+    }
+}
+
+impl CompiledMesh {
+    /// Search control value starting from current values of controls
+    ///
+    /// Running mesh is not affected, found value may be applied by `set_ctrl`.
+    pub fn seek(&self, decls: &NodeDecls, goal: &GoalSeek) -> Result<Solution, String> {
+        goal.solve(decls, self.mesh(), &self.ctrl_values())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{HashMap};
+    use super::{GoalSeek};
+    use dsl::{Mesh, Link, Value, NodeDecls, compile};
+    use ops::{basic_ops};
+    use serde_json::{from_str};
+    use futures::{Stream};
+    use futures::stream::{empty};
+    use tokio::executor::current_thread::{block_on_all};
+
+    #[test]
+    fn test_goal_seek() {
+        let ops = NodeDecls::new().with(basic_ops);
+
+        let mesh: Mesh = from_str(r#"{
+  "nodes": [
+    { "name": "mul", "kind": "*", "ins": [
+      { "name": "a", "link": { "name": "a" } },
+      { "name": "b", "link": { "name": "b" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "add", "kind": "+", "ins": [
+      { "name": "a", "link": { "node": "mul", "out": "=" } },
+      { "name": "b", "link": { "name": "c" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "inv", "kind": "^-1", "ins": [
+      { "name": "a", "link": { "node": "add", "out": "=" } }
+    ], "outs": [
+      { "name": "=" }
+    ] }
+  ],
+  "ctrls": [
+    { "name": "a", "value": "2" },
+    { "name": "b", "value": "3" },
+    { "name": "c", "value": "1", "const": true }
+  ]
+}"#).unwrap();
+
+        let (add, inv) = (Link::output("add", "="), Link::output("inv", "="));
+        let point = HashMap::new();
+
+        assert_eq!(mesh.evaluate(&ops, &point, &[add.clone(), inv.clone()]),
+                   Ok(vec![7.into(), Value::from(1) / Value::from(7)]));
+
+        let solution = GoalSeek::new("a", add.clone(), 13.into(), 0.into(), 10.into())
+            .solve(&ops, &mesh, &point).unwrap();
+
+        assert_eq!(solution.value, 4.into());
+        assert_eq!(solution.output, 13.into());
+        assert!(solution.converged);
+
+        // a*3 + 1 = 10
+        let solution = GoalSeek::new("a", inv.clone(), "0.1".parse().unwrap(), 0.into(), 10.into())
+            .solve(&ops, &mesh, &point).unwrap();
+
+        assert!(solution.converged);
+        assert!((solution.value - 3.into()).abs() < "0.000001".parse().unwrap());
+
+        let solution = GoalSeek::new("a", inv.clone(), "0.1".parse().unwrap(), 0.into(), 10.into())
+            .with_tolerance(0.into())
+            .with_max_iters(2)
+            .solve(&ops, &mesh, &point).unwrap();
+
+        assert!(!solution.converged);
+        assert_eq!(solution.iters, 2);
+
+        assert!(GoalSeek::new("a", add.clone(), 100.into(), 0.into(), 10.into()).solve(&ops, &mesh, &point).is_err());
+        assert!(GoalSeek::new("c", add.clone(), 13.into(), 0.into(), 10.into()).solve(&ops, &mesh, &point).is_err());
+        assert!(GoalSeek::new("a", add.clone(), 13.into(), 10.into(), 0.into()).solve(&ops, &mesh, &point).is_err());
+
+        let mut compiled = compile(&ops, &mesh, Box::new(empty())).unwrap();
+        compiled.set_ctrl("b", 4).unwrap();
+        let out = compiled.subscribe_all();
+        compiled.shutdown();
+        block_on_all(out.collect()).unwrap();
+
+        let solution = compiled.seek(&ops, &GoalSeek::new("a", add.clone(), 13.into(), 0.into(), 10.into())).unwrap();
+
+        assert_eq!(solution.value, 3.into());
+        assert_eq!(compiled.get(&add), Some(9.into()));
+    }
+}
@@ -0,0 +1,344 @@
+use std::rc::{Rc, Weak};
+use std::sync::{Arc, Mutex};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeSet, VecDeque};
+
+use futures::{Stream, Poll, Async};
+use futures::future::{poll_fn};
+use futures::executor::{self, Notify, NotifyHandle};
+use futures::task::{self, Task};
+
+/// Items queued for receiver
+struct Feed<T> {
+    items: VecDeque<T>,
+    task: Option<Task>,
+    /// Oldest items are dropped when queue is full
+    capacity: Option<usize>,
+}
+
+impl<T> Feed<T> {
+    fn push(&mut self, item: T, bound: Option<usize>) {
+        if let Some(capacity) = self.capacity.or(bound) {
+            while self.items.len() >= capacity.max(1) {
+                self.items.pop_front();
+            }
+        }
+        self.items.push_back(item);
+        self.notify();
+    }
+
+    fn notify(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.notify();
+        }
+    }
+}
+
+struct Shared<S: Stream> {
+    source: Option<S>,
+    /// Error which ended source, it is reported once to each receiver
+    error: Option<S::Error>,
+    feeds: Vec<Weak<RefCell<Feed<S::Item>>>>,
+    /// Capacity of receivers which have none, set when source is polled by driver
+    bound: Option<usize>,
+}
+
+impl<S: Stream> Shared<S> where S::Item: Clone {
+    fn push(&mut self, item: S::Item) {
+        self.feeds.retain(|feed| feed.upgrade().is_some());
+        for feed in self.feeds.iter().filter_map(Weak::upgrade) {
+            feed.borrow_mut().push(item.clone(), self.bound);
+        }
+    }
+}
+
+impl<S: Stream> Shared<S> {
+    /// wake all receivers, so one of them will poll source
+    fn notify_all(&self) {
+        for feed in self.feeds.iter().filter_map(Weak::upgrade) {
+            if let Ok(mut feed) = feed.try_borrow_mut() {
+                feed.notify();
+            }
+        }
+    }
+
+    fn is_driven(&self) -> bool {
+        self.bound.is_some()
+    }
+}
+
+/// Stream which items are delivered to many receivers
+///
+/// Each receiver gets items which are polled after it was created.
+/// The source is polled by any receiver which runs out of queued items,
+/// unless the stream is polled by `Driver`, so the handle itself neither polls nor buffers anything.
+pub struct SharedStream<S: Stream> {
+    shared: Rc<RefCell<Shared<S>>>,
+}
+
+impl<S: Stream> Clone for SharedStream<S> {
+    fn clone(&self) -> Self {
+        Self { shared: self.shared.clone() }
+    }
+}
+
+impl<S: Stream> SharedStream<S> where S::Item: Clone {
+    pub fn new(source: S) -> Self {
+        Self { shared: Rc::new(RefCell::new(Shared { source: Some(source), error: None, feeds: Vec::new(), bound: None })) }
+    }
+
+    /// receiver which gets all items
+    pub fn receive(&self) -> Receiver<S> {
+        self.receiver(None)
+    }
+
+    /// receiver which keeps only the latest items when it is not polled fast enough
+    pub fn receive_latest(&self, capacity: usize) -> Receiver<S> {
+        self.receiver(Some(capacity))
+    }
+
+    fn receiver(&self, capacity: Option<usize>) -> Receiver<S> {
+        let feed = Rc::new(RefCell::new(Feed { items: VecDeque::new(), task: None, capacity }));
+        self.shared.borrow_mut().feeds.push(Rc::downgrade(&feed));
+        Receiver { shared: self.shared.clone(), feed, failed: false }
+    }
+}
+
+/// Receiver of shared stream
+pub struct Receiver<S: Stream> {
+    shared: Rc<RefCell<Shared<S>>>,
+    feed: Rc<RefCell<Feed<S::Item>>>,
+    /// Error of source is already reported
+    failed: bool,
+}
+
+impl<S: Stream> Receiver<S> where S::Error: Clone {
+    /// end of driven source, which is reported after queued items
+    fn ended(&mut self) -> Poll<Option<S::Item>, S::Error> {
+        let shared = self.shared.borrow();
+        if shared.source.is_some() {
+            self.feed.borrow_mut().task = Some(task::current());
+            return Ok(Async::NotReady);
+        }
+        match shared.error {
+            Some(ref err) if !self.failed => {
+                self.failed = true;
+                Err(err.clone())
+            },
+            _ => Ok(Async::Ready(None)),
+        }
+    }
+}
+
+impl<S: Stream> Stream for Receiver<S> where S::Item: Clone, S::Error: Clone {
+    type Item = S::Item;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<S::Item>, S::Error> {
+        loop {
+            if let Some(item) = self.feed.borrow_mut().items.pop_front() {
+                return Ok(Async::Ready(Some(item)));
+            }
+
+            // driven source is never polled by receivers, so they do not nest
+            if self.shared.borrow().is_driven() {
+                return self.ended();
+            }
+
+            let mut shared = self.shared.borrow_mut();
+            let polled = match shared.source {
+                Some(ref mut source) => source.poll()?,
+                None => return Ok(Async::Ready(None)),
+            };
+
+            match polled {
+                Async::Ready(Some(item)) => shared.push(item),
+                Async::Ready(None) => {
+                    shared.source = None;
+                    shared.notify_all();
+                },
+                Async::NotReady => {
+                    self.feed.borrow_mut().task = Some(task::current());
+                    return Ok(Async::NotReady);
+                },
+            }
+        }
+    }
+}
+
+impl<S: Stream> Drop for Receiver<S> {
+    fn drop(&mut self) {
+        // source may remember only the task of this receiver
+        if let Ok(shared) = self.shared.try_borrow() {
+            shared.notify_all();
+        }
+    }
+}
+
+/// Shared stream as seen by driver
+trait Source {
+    /// poll source once, true when it yielded item and may have more
+    fn pump(&self) -> bool;
+
+    /// number of items which wait in receivers
+    fn queued(&self) -> usize;
+}
+
+impl<S: Stream> Source for RefCell<Shared<S>> where S::Item: Clone {
+    fn pump(&self) -> bool {
+        let mut shared = self.borrow_mut();
+        let polled = match shared.source {
+            Some(ref mut source) => source.poll(),
+            None => return false,
+        };
+
+        match polled {
+            Ok(Async::Ready(Some(item))) => {
+                shared.push(item);
+                true
+            },
+            Ok(Async::Ready(None)) => {
+                shared.source = None;
+                shared.notify_all();
+                false
+            },
+            Ok(Async::NotReady) => false,
+            Err(err) => {
+                shared.source = None;
+                shared.error = Some(err);
+                shared.notify_all();
+                false
+            },
+        }
+    }
+
+    fn queued(&self) -> usize {
+        self.borrow().feeds.iter().filter_map(Weak::upgrade).map(|feed| feed.borrow().items.len()).sum()
+    }
+}
+
+/// Sources which need to be polled and tasks which wait for them
+struct Wakeup {
+    dirty: Mutex<BTreeSet<usize>>,
+    tasks: Mutex<Vec<Task>>,
+}
+
+impl Notify for Wakeup {
+    fn notify(&self, id: usize) {
+        self.dirty.lock().unwrap().insert(id);
+        for task in self.tasks.lock().unwrap().drain(..) {
+            task.notify();
+        }
+    }
+}
+
+/// Scheduler which polls shared streams in place of their receivers
+///
+/// Streams are added in topological order and polled only when something they wait for is ready.
+/// Each stream polls only queued items of the streams it depends on, so items propagate
+/// through any number of streams without nesting polls, and each queue between streams holds
+/// at most `capacity` items. Streams yield one item at a time and consumers are polled before
+/// their sources, so each item reaches all consumers before the next one is produced.
+pub struct Driver {
+    sources: RefCell<Vec<Rc<Source>>>,
+    wakeup: Arc<Wakeup>,
+    capacity: usize,
+    running: Cell<bool>,
+}
+
+impl Driver {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            sources: RefCell::new(Vec::new()),
+            wakeup: Arc::new(Wakeup { dirty: Mutex::new(BTreeSet::new()), tasks: Mutex::new(Vec::new()) }),
+            capacity: capacity.max(1),
+            running: Cell::new(false),
+        }
+    }
+
+    /// poll stream by driver after the streams which are added before
+    pub fn add<S>(&self, stream: &SharedStream<S>)
+    where S: Stream + 'static, S::Item: Clone + 'static, S::Error: 'static
+    {
+        stream.shared.borrow_mut().bound = Some(self.capacity);
+        let mut sources = self.sources.borrow_mut();
+        self.wakeup.dirty.lock().unwrap().insert(sources.len());
+        sources.push(stream.shared.clone());
+    }
+
+    /// poll streams until none of them is ready
+    ///
+    /// The latest added stream which is ready is polled first, so queues are drained before they are refilled.
+    /// Nested calls return at once, the outer one picks up what they were called for.
+    pub fn run(&self) {
+        if self.running.get() { return; }
+        self.running.set(true);
+
+        let handle = NotifyHandle::from(self.wakeup.clone());
+        loop {
+            let next = {
+                let mut dirty = self.wakeup.dirty.lock().unwrap();
+                let last = dirty.iter().next_back().cloned();
+                if let Some(id) = last { dirty.remove(&id); }
+                last
+            };
+            let id = match next { Some(id) => id, None => break };
+
+            let source = self.sources.borrow()[id].clone();
+            let pumped = executor::spawn(poll_fn(move || Ok::<_, ()>(Async::Ready(source.pump()))))
+                .poll_future_notify(&handle, id);
+            if let Ok(Async::Ready(true)) = pumped {
+                self.wakeup.dirty.lock().unwrap().insert(id);
+            }
+        }
+
+        self.running.set(false);
+    }
+
+    /// number of items which wait in receivers of all streams
+    pub fn queued(&self) -> usize {
+        self.sources.borrow().iter().map(|source| source.queued()).sum()
+    }
+
+    /// wake current task when some stream becomes ready
+    pub fn wait(&self) {
+        let ready = !self.wakeup.dirty.lock().unwrap().is_empty();
+        if ready {
+            task::current().notify();
+        } else {
+            self.wakeup.tasks.lock().unwrap().push(task::current());
+        }
+    }
+}
+
+impl Drop for Driver {
+    fn drop(&mut self) {
+        // streams hold receivers of streams added before, dropping them from the last one
+        // releases each stream while others still hold their sources, so drops do not nest
+        let mut sources = self.sources.borrow_mut();
+        while let Some(source) = sources.pop() {
+            drop(source);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SharedStream};
+    use futures::{Stream};
+    use futures::stream::{iter_ok};
+
+    #[test]
+    fn test_shared_stream() {
+        let shared = SharedStream::new(iter_ok::<_, ()>(vec![1, 2, 3, 4]));
+
+        let all = shared.receive();
+        let latest = shared.receive_latest(2);
+
+        assert_eq!(all.wait().collect::<Result<Vec<_>, _>>(), Ok(vec![1, 2, 3, 4]));
+        assert_eq!(latest.wait().collect::<Result<Vec<_>, _>>(), Ok(vec![3, 4]));
+
+        // source is already consumed
+        assert_eq!(shared.receive().wait().collect::<Result<Vec<_>, _>>(), Ok(vec![]));
+    }
+}
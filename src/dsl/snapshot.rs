@@ -0,0 +1,133 @@
+use serde_json::{self, Value as Json};
+
+use dsl::{Link, Value, InputControl};
+
+/// Value of link at the moment of snapshot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LinkValue {
+    pub link: Link,
+    #[serde(with = "::dsl::value")]
+    pub value: Value,
+}
+
+/// Internal state of node at the moment of snapshot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeSnapshot {
+    pub name: String,
+    pub state: Json,
+}
+
+/// Runtime state of compiled mesh
+///
+/// Holds values of controls, known values of links and internal states of stateful nodes.
+/// Mesh may be compiled again resuming from this state using `resume`.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Snapshot {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ctrls: Vec<InputControl>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<LinkValue>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nodes: Vec<NodeSnapshot>,
+}
+
+impl Snapshot {
+    pub fn from_json<S: AsRef<str>>(json: S) -> Result<Self, String> {
+        serde_json::from_str(json.as_ref()).map_err(|err| err.to_string())
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|err| err.to_string())
+    }
+
+    pub fn get_ctrl<S: AsRef<str>>(&self, name: S) -> Option<Value> {
+        self.ctrls.iter().find(|ctrl| ctrl.name == name.as_ref()).map(|ctrl| ctrl.value)
+    }
+
+    pub fn get_value(&self, link: &Link) -> Option<Value> {
+        self.values.iter().find(|value| value.link == *link).map(|value| value.value)
+    }
+
+    pub fn get_node<S: AsRef<str>>(&self, name: S) -> Option<&Json> {
+        self.nodes.iter().find(|node| node.name == name.as_ref()).map(|node| &node.state)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::rc::{Rc};
+    use std::cell::{RefCell};
+    use super::{Snapshot};
+    use dsl::{Mesh, Link, Value, NodeKind, InputKind, OutputKind, NodeDecl, NodeDecls, Observables, compile, resume};
+    use serde_json::{from_str};
+    use futures::{Stream};
+    use futures::stream::{empty};
+    use tokio::executor::current_thread::{block_on_all};
+
+    fn count_impl(mut ins: Observables) -> Observables {
+        let (_, ai) = ins.get("a").into();
+        let count = Rc::new(RefCell::new(0u64));
+        let count2 = count.clone();
+
+        let ro = Box::new(ai.map(move |a| {
+            *count2.borrow_mut() += 1;
+            a.map(|_| Value::from(*count2.borrow()))
+        }));
+
+        Observables::new().put("=", ro).with_state(count)
+    }
+
+    #[test]
+    fn test_snapshot_resume() {
+        let ops = NodeDecls::new()
+            .add(NodeDecl::new(
+                NodeKind::new("count")
+                    .with_in(InputKind::new("a"))
+                    .with_out(OutputKind::new("=")),
+                count_impl));
+
+        let mesh: Mesh = from_str(r#"{
+  "nodes": [
+    { "name": "count", "kind": "count", "ins": [
+      { "name": "a", "link": { "name": "x" } }
+    ], "outs": [
+      { "name": "=" }
+    ] }
+  ],
+  "ctrls": [
+    { "name": "x", "value": "1" }
+  ]
+}"#).unwrap();
+
+        let count = Link::output("count", "=");
+
+        let mut compiled = compile(&ops, &mesh, Box::new(empty())).unwrap();
+        compiled.set_ctrl("x", 2).unwrap();
+        let out = compiled.subscribe_all();
+        compiled.shutdown();
+        block_on_all(out.collect()).unwrap();
+
+        let snapshot = compiled.save_state().unwrap();
+
+        assert_eq!(snapshot.get_ctrl("x"), Some(2.into()));
+        assert_eq!(snapshot.get_value(&count), Some(2.into()));
+        assert_eq!(snapshot.get_node("count"), Some(&json!(2)));
+        assert_eq!(Snapshot::from_json(snapshot.to_json().unwrap()), Ok(snapshot.clone()));
+
+        let mut compiled = resume(&ops, &mesh, Box::new(empty()), &snapshot).unwrap();
+
+        // saved values are available before mesh runs
+        assert_eq!(compiled.get(&count), Some(2.into()));
+
+        let out = compiled.subscribe_all();
+        compiled.shutdown();
+        block_on_all(out.collect()).unwrap();
+
+        assert_eq!(compiled.get(&Link::ctrl("x")), Some(2.into()));
+        assert_eq!(compiled.get(&count), Some(3.into()));
+
+        let mut snapshot = snapshot;
+        snapshot.nodes[0].state = json!("many");
+        assert!(resume(&ops, &mesh, Box::new(empty()), &snapshot).is_err());
+    }
+}
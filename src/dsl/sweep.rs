@@ -0,0 +1,225 @@
+use std::fs::{File};
+use std::io::{Read};
+use std::path::{Path};
+
+use dsl::{Mesh, Value, NodeDecls, Format, Table, batch};
+
+/// Evenly spaced values of control
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Range {
+    pub ctrl: String,
+    #[serde(with = "::dsl::value")]
+    pub from: Value,
+    #[serde(with = "::dsl::value")]
+    pub to: Value,
+    /// Number of intervals, so range has one value more including both ends
+    pub steps: usize,
+}
+
+impl Range {
+    pub fn new<S: Into<String>>(ctrl: S, from: Value, to: Value, steps: usize) -> Self {
+        Self { ctrl: ctrl.into(), from, to, steps }
+    }
+
+    pub fn values(&self) -> Vec<Value> {
+        if self.steps == 0 {
+            return vec![self.from];
+        }
+        let steps = Value::from(self.steps as u64);
+        (0..self.steps + 1)
+            .map(|i| self.from + (self.to - self.from) * Value::from(i as u64) / steps)
+            .collect()
+    }
+}
+
+/// How values of ranges are combined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SweepMode {
+    /// Every combination of values
+    Grid,
+    /// Values at the same positions, ranges must have the same number of steps
+    Zip,
+}
+
+impl Default for SweepMode {
+    fn default() -> Self {
+        SweepMode::Grid
+    }
+}
+
+/// Evaluation of outputs over ranges of controls
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Sweep {
+    pub ranges: Vec<Range>,
+    #[serde(default)]
+    pub mode: SweepMode,
+    /// Links in textual form like `node.out` or `$ctrl`, or names of exports
+    pub outputs: Vec<String>,
+}
+
+impl Sweep {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_range(mut self, range: Range) -> Self {
+        self.ranges.push(range);
+        self
+    }
+
+    pub fn with_mode(mut self, mode: SweepMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn with_output<S: Into<String>>(mut self, output: S) -> Self {
+        self.outputs.push(output.into());
+        self
+    }
+
+    /// Load sweep from file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let mut data = Vec::new();
+
+        File::open(path)
+            .and_then(|mut file| file.read_to_end(&mut data))
+            .map_err(|err| format!("Unable to read `{}`: {}", path.display(), err))?;
+
+        let format = Format::from_path(path).or_else(|| Format::detect(&data))
+            .ok_or_else(|| format!("Unknown sweep format of `{}`", path.display()))?;
+
+        format.deserialize(&data)
+    }
+
+    /// Combinations of control values, a column per range
+    ///
+    /// In grid mode the first range changes slowest.
+    pub fn points(&self) -> Result<Table, String> {
+        let mut table = Table::new(Vec::new());
+
+        for range in &self.ranges {
+            if table.columns.contains(&range.ctrl) {
+                return Err(format!("Control `{}` is swept twice", range.ctrl));
+            }
+            table.columns.push(range.ctrl.clone());
+        }
+
+        let values: Vec<_> = self.ranges.iter().map(Range::values).collect();
+
+        table.rows = match self.mode {
+            SweepMode::Grid => values.iter().fold(vec![Vec::new()], |rows, values| {
+                rows.iter().flat_map(|row| values.iter().map(move |value| {
+                    let mut row = row.clone();
+                    row.push(*value);
+                    row
+                })).collect()
+            }),
+            SweepMode::Zip => {
+                let len = values.first().map(Vec::len).unwrap_or(1);
+                if values.iter().any(|values| values.len() != len) {
+                    return Err("Zipped ranges must have the same number of steps".into());
+                }
+                (0..len).map(|i| values.iter().map(|values| values[i]).collect()).collect()
+            },
+        };
+
+        Ok(table)
+    }
+
+    /// Evaluate outputs at every point
+    ///
+    /// Result has columns of swept controls followed by columns of outputs,
+    /// it may be written as CSV by `Table::to_csv` or `Table::save`.
+    pub fn run(&self, decls: &NodeDecls, mesh: &Mesh) -> Result<Table, String> {
+        let links = self.outputs.iter()
+            .map(|output| mesh.resolve_link(output))
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let points = self.points()?;
+        let values = batch(decls, mesh, &points, &links)?;
+
+        let mut table = Table::new(points.columns.iter().chain(&self.outputs).cloned().collect());
+
+        table.rows = points.rows.into_iter().zip(values.rows)
+            .map(|(mut row, values)| {
+                row.extend(values);
+                row
+            })
+            .collect();
+
+        Ok(table)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Sweep, SweepMode, Range};
+    use dsl::{Mesh, NodeDecls};
+    use ops::{basic_ops};
+    use serde_json::{from_str};
+
+    #[test]
+    fn test_sweep() {
+        let ops = NodeDecls::new().with(basic_ops);
+
+        let mesh: Mesh = from_str(r#"{
+  "nodes": [
+    { "name": "mul", "kind": "*", "ins": [
+      { "name": "a", "link": { "name": "a" } },
+      { "name": "b", "link": { "name": "b" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "add", "kind": "+", "ins": [
+      { "name": "a", "link": { "node": "mul", "out": "=" } },
+      { "name": "b", "link": { "name": "c" } }
+    ], "outs": [
+      { "name": "=" }
+    ] }
+  ],
+  "ctrls": [
+    { "name": "a", "value": "2" },
+    { "name": "b", "value": "3" },
+    { "name": "c", "value": "1" }
+  ],
+  "exports": [
+    { "name": "sum", "link": { "node": "add", "out": "=" } }
+  ]
+}"#).unwrap();
+
+        assert_eq!(Range::new("a", 0.into(), 1.into(), 4).values().iter().map(|value| value.to_string()).collect::<Vec<_>>(),
+                   vec!["0", "0.25", "0.5", "0.75", "1"]);
+
+        let sweep: Sweep = from_str(r#"{
+  "ranges": [
+    { "ctrl": "a", "from": "0", "to": "2", "steps": 2 },
+    { "ctrl": "b", "from": 1, "to": 2, "steps": 1 }
+  ],
+  "outputs": ["sum", "mul.="]
+}"#).unwrap();
+
+        assert_eq!(sweep.run(&ops, &mesh).unwrap().to_csv(), "a,b,sum,mul.=\n\
+                                                            0,1,1,0\n\
+                                                            0,2,1,0\n\
+                                                            1,1,2,1\n\
+                                                            1,2,3,2\n\
+                                                            2,1,3,2\n\
+                                                            2,2,5,4\n");
+
+        let zipped = sweep.clone().with_mode(SweepMode::Zip);
+        assert!(zipped.run(&ops, &mesh).is_err());
+
+        let zipped = Sweep::new()
+            .with_mode(SweepMode::Zip)
+            .with_range(Range::new("a", 0.into(), 2.into(), 2))
+            .with_range(Range::new("c", 2.into(), 0.into(), 2))
+            .with_output("sum");
+
+        assert_eq!(zipped.run(&ops, &mesh).unwrap().to_csv(), "a,c,sum\n0,2,2\n1,1,4\n2,0,6\n");
+
+        assert!(sweep.clone().with_range(Range::new("a", 0.into(), 1.into(), 1)).run(&ops, &mesh).is_err());
+        assert!(sweep.clone().with_output("nothing").run(&ops, &mesh).is_err());
+    }
+}
@@ -0,0 +1,311 @@
+use std::fmt;
+use std::collections::{HashMap};
+
+use dsl::{Mesh, Link, Value, NodeDecls};
+
+/// Symbolic expression of link value in terms of controls
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// Known value, like constant control
+    Const(Value),
+    /// Value of control
+    Var(String),
+    Neg(Box<Expr>),
+    Inv(Box<Expr>),
+    Add(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
+    /// Output of kind which has no symbolic form
+    Apply {
+        kind: String,
+        out: String,
+        args: Vec<Expr>,
+    },
+}
+
+/// Constructors apply light simplification: constants are folded,
+/// `x + 0`, `x * 1`, `-(-x)` and `(x^-1)^-1` are reduced.
+/// Products with zero are folded only when both factors are constants.
+impl Expr {
+    pub fn var<S: Into<String>>(name: S) -> Self {
+        Expr::Var(name.into())
+    }
+
+    pub fn neg(a: Expr) -> Self {
+        match a {
+            Expr::Const(a) => Expr::Const(-a),
+            Expr::Neg(a) => *a,
+            a => Expr::Neg(Box::new(a)),
+        }
+    }
+
+    pub fn inv(a: Expr) -> Self {
+        match a {
+            Expr::Const(a) if !a.is_zero() => Expr::Const(Value::from(1) / a),
+            Expr::Inv(a) => *a,
+            a => Expr::Inv(Box::new(a)),
+        }
+    }
+
+    pub fn add(a: Expr, b: Expr) -> Self {
+        match (a, b) {
+            (Expr::Const(a), Expr::Const(b)) => Expr::Const(a + b),
+            (a, b) => if a.is_const(0) { b }
+            else if b.is_const(0) { a }
+            else { Expr::Add(Box::new(a), Box::new(b)) },
+        }
+    }
+
+    pub fn mul(a: Expr, b: Expr) -> Self {
+        match (a, b) {
+            (Expr::Const(a), Expr::Const(b)) => Expr::Const(a * b),
+            // `x*0` is kept since `x` may turn out infinite or NaN
+            (a, b) => if a.is_const(1) { b }
+            else if b.is_const(1) { a }
+            else { Expr::Mul(Box::new(a), Box::new(b)) },
+        }
+    }
+
+    pub fn apply<S: Into<String>>(kind: S, out: S, args: Vec<Expr>) -> Self {
+        Expr::Apply { kind: kind.into(), out: out.into(), args }
+    }
+
+    fn is_const(&self, value: i32) -> bool {
+        match *self {
+            Expr::Const(a) => a == Value::from(value),
+            _ => false,
+        }
+    }
+
+    /// binding strength used to put parentheses
+    fn precedence(&self) -> u8 {
+        match *self {
+            Expr::Add(..) => 1,
+            Expr::Mul(..) => 2,
+            Expr::Neg(..) => 3,
+            Expr::Const(value) if value.is_negative() => 3,
+            Expr::Inv(..) => 4,
+            _ => 5,
+        }
+    }
+
+    fn fmt_prec(&self, f: &mut fmt::Formatter, prec: u8) -> fmt::Result {
+        if self.precedence() < prec {
+            f.write_str("(")?;
+            self.fmt_prec(f, 0)?;
+            return f.write_str(")");
+        }
+
+        match *self {
+            Expr::Const(value) => write!(f, "{}", value),
+            Expr::Var(ref name) => f.write_str(name),
+            Expr::Neg(ref a) => {
+                f.write_str("-")?;
+                a.fmt_prec(f, 4)
+            },
+            Expr::Inv(ref a) => {
+                a.fmt_prec(f, 5)?;
+                f.write_str("^-1")
+            },
+            Expr::Add(ref a, ref b) => {
+                a.fmt_prec(f, 1)?;
+                match **b {
+                    Expr::Neg(ref b) => {
+                        f.write_str(" - ")?;
+                        b.fmt_prec(f, 2)
+                    },
+                    ref b => {
+                        f.write_str(" + ")?;
+                        b.fmt_prec(f, 2)
+                    },
+                }
+            },
+            Expr::Mul(ref a, ref b) => {
+                a.fmt_prec(f, 2)?;
+                match **b {
+                    Expr::Inv(ref b) => {
+                        f.write_str("/")?;
+                        b.fmt_prec(f, 3)
+                    },
+                    ref b => {
+                        f.write_str("*")?;
+                        b.fmt_prec(f, 4)
+                    },
+                }
+            },
+            Expr::Apply { ref kind, ref out, ref args } => {
+                write!(f, "{}(", kind)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 { f.write_str(", ")?; }
+                    arg.fmt_prec(f, 0)?;
+                }
+                f.write_str(")")?;
+                if out != "=" { write!(f, ".{}", out)?; }
+                Ok(())
+            },
+        }
+    }
+}
+
+/// Printable formula like `a*b + c`
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_prec(f, 0)
+    }
+}
+
+impl Mesh {
+    /// Express outputs as formulas in terms of controls
+    ///
+    /// Exported links are expressed when the mesh has exports, otherwise all outputs of nodes.
+    /// Constant controls are substituted by their values.
+    pub fn formulas(&self, decls: &NodeDecls) -> Result<Vec<(Link, Expr)>, String> {
+        let exprs = self.symbolic(decls)?;
+
+        let links: Vec<_> = if self.exports.is_empty() {
+            self.nodes.iter()
+                .flat_map(|node| node.outs.iter().map(move |output| Link::output(node.name.clone(), output.name.clone())))
+                .collect()
+        } else {
+            self.exports.iter().map(|export| export.link.clone()).collect()
+        };
+
+        links.into_iter().map(|link| {
+            let expr = exprs.get(&link).cloned()
+                .ok_or_else(|| format!("Unknown link `{}`", link))?;
+            Ok((link, expr))
+        }).collect()
+    }
+
+    /// Express link as formula in terms of controls
+    pub fn formula(&self, decls: &NodeDecls, link: &Link) -> Result<Expr, String> {
+        self.symbolic(decls)?.remove(link).ok_or_else(|| format!("Unknown link `{}`", link))
+    }
+
+    fn symbolic(&self, decls: &NodeDecls) -> Result<HashMap<Link, Expr>, String> {
+        let mut exprs: HashMap<Link, Expr> = self.ctrls.iter().map(|ctrl| {
+            let expr = if ctrl.is_constant() { Expr::Const(ctrl.value) } else { Expr::var(ctrl.name.clone()) };
+            (Link::ctrl(ctrl.name.clone()), expr)
+        }).collect();
+
+        for node in self.topo_order()? {
+            let decl = decls.get(&node.kind)
+                .ok_or_else(|| format!("Unsupported node kind `{}`", node.kind))?;
+
+            let args = decl.def.ins.iter().map(|kind| {
+                let input = node.get_in(&kind.name)
+                    .ok_or_else(|| format!("Missing input `{}` of node `{}`", kind.name, node.name))?;
+                exprs.get(&input.link).cloned()
+                    .ok_or_else(|| format!("Unknown link `{}` of input `{}` of node `{}`", input.link, input.name, node.name))
+            }).collect::<Result<Vec<_>, String>>()?;
+
+            let outs = decl.symbolic(&args).unwrap_or_else(|| {
+                decl.def.outs.iter()
+                    .map(|kind| Expr::apply(node.kind.clone(), kind.name.clone(), args.clone()))
+                    .collect()
+            });
+
+            for (kind, expr) in decl.def.outs.iter().zip(outs) {
+                exprs.insert(Link::output(node.name.clone(), kind.name.clone()), expr);
+            }
+        }
+
+        Ok(exprs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Expr};
+    use dsl::{Mesh, Link, NodeDecls};
+    use dsl::testing::{opaque_ops};
+    use ops::{basic_ops};
+    use serde_json::{from_str};
+
+    #[test]
+    fn test_formulas() {
+        let ops = NodeDecls::new().with(basic_ops).with(opaque_ops);
+
+        let mesh: Mesh = from_str(r#"{
+  "nodes": [
+    { "name": "mul", "kind": "*", "ins": [
+      { "name": "a", "link": { "name": "a" } },
+      { "name": "b", "link": { "name": "b" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "add", "kind": "+", "ins": [
+      { "name": "a", "link": { "node": "mul", "out": "=" } },
+      { "name": "b", "link": { "name": "c" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "neg", "kind": "-", "ins": [
+      { "name": "a", "link": { "name": "c" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "sub", "kind": "+", "ins": [
+      { "name": "a", "link": { "node": "add", "out": "=" } },
+      { "name": "b", "link": { "node": "neg", "out": "=" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "inv", "kind": "^-1", "ins": [
+      { "name": "a", "link": { "node": "sub", "out": "=" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "div", "kind": "*", "ins": [
+      { "name": "a", "link": { "name": "one" } },
+      { "name": "b", "link": { "node": "inv", "out": "=" } }
+    ], "outs": [
+      { "name": "=" }
+    ] },
+    { "name": "max", "kind": "max", "ins": [
+      { "name": "a", "link": { "node": "div", "out": "=" } },
+      { "name": "b", "link": { "name": "two" } }
+    ], "outs": [
+      { "name": "=" }
+    ] }
+  ],
+  "ctrls": [
+    { "name": "a", "value": "2" },
+    { "name": "b", "value": "3" },
+    { "name": "c", "value": "1" },
+    { "name": "one", "value": "1", "const": true },
+    { "name": "two", "value": "2", "const": true }
+  ]
+}"#).unwrap();
+
+        let formulas = mesh.formulas(&ops).unwrap();
+
+        assert_eq!(formulas.iter().map(|&(ref link, ref expr)| format!("{} = {}", link, expr)).collect::<Vec<_>>(), vec![
+            "mul.= = a*b",
+            "add.= = a*b + c",
+            "neg.= = -c",
+            "sub.= = a*b + c - c",
+            "inv.= = (a*b + c - c)^-1",
+            "div.= = (a*b + c - c)^-1",
+            "max.= = max((a*b + c - c)^-1, 2)",
+        ]);
+
+        assert_eq!(mesh.formula(&ops, &Link::output("mul", "=")),
+                   Ok(Expr::mul(Expr::var("a"), Expr::var("b"))));
+        assert!(mesh.formula(&ops, &Link::output("mul", "-")).is_err());
+    }
+
+    #[test]
+    fn test_expr_display() {
+        let (a, b, c) = (Expr::var("a"), Expr::var("b"), Expr::var("c"));
+
+        assert_eq!(Expr::mul(Expr::add(a.clone(), b.clone()), c.clone()).to_string(), "(a + b)*c");
+        assert_eq!(Expr::mul(a.clone(), Expr::inv(Expr::add(b.clone(), c.clone()))).to_string(), "a/(b + c)");
+        assert_eq!(Expr::neg(Expr::neg(a.clone())), a);
+        assert_eq!(Expr::add(Expr::Const(2.into()), Expr::Const(3.into())), Expr::Const(5.into()));
+        assert_eq!(Expr::mul(a.clone(), Expr::Const(0.into())).to_string(), "a*0");
+        assert_eq!(Expr::mul(Expr::Const(2.into()), Expr::Const(0.into())), Expr::Const(0.into()));
+        assert_eq!(Expr::add(a.clone(), Expr::neg(Expr::mul(b, c))).to_string(), "a - b*c");
+        assert_eq!(Expr::mul(Expr::Const((-2).into()), a.clone()).to_string(), "-2*a");
+        assert_eq!(Expr::mul(a.clone(), Expr::neg(Expr::var("b"))).to_string(), "a*(-b)");
+    }
+}
@@ -0,0 +1,27 @@
+use futures::{Stream};
+
+use dsl::{NodeKind, InputKind, OutputKind, Signal, Observables, NodeDecl, NodeDecls};
+
+fn max_impl(mut ins: Observables) -> Observables {
+    let (av, ai) = ins.get("a").into();
+    let (bv, bi) = ins.get("b").into();
+
+    let ro = Box::new(ai.map(|a| a.cause).select(bi.map(|b| b.cause)).map(move |cause| {
+        match (*av.borrow(), *bv.borrow()) {
+            (Some(a), Some(b)) => Some(Signal::new(if a > b { a } else { b }).with_cause(cause)),
+            _ => None,
+        }
+    }).skip_while(|opt| Ok(opt.is_none())).map(Option::unwrap));
+
+    Observables::new().put("=", ro)
+}
+
+/// Kinds which only run, so analyses of mesh can not see through them
+pub fn opaque_ops(decls: &mut NodeDecls) {
+    decls.put(NodeDecl::new(
+        NodeKind::new("max")
+            .with_in(InputKind::new("a"))
+            .with_in(InputKind::new("b"))
+            .with_out(OutputKind::new("=")),
+        max_impl));
+}
@@ -0,0 +1,236 @@
+//! Serialization of decimal values
+//!
+//! Values are accepted both as strings (`"2.5"`) and as numbers (`2.5`).
+//! Numbers are parsed from their textual form when the format keeps it
+//! (like `serde_json` with `arbitrary_precision`), so no precision is lost.
+//!
+//! Use it on `Value` fields with `#[serde(with = "::dsl::value")]`.
+//! Values are serialized as strings unless other style is requested
+//! by `to_json` or `Format::serialize_styled`.
+
+use std::cell::{Cell};
+use std::fmt;
+use std::str::{FromStr};
+
+use serde::{Serialize, Serializer, Deserializer};
+use serde::de::{self, Visitor, MapAccess};
+use serde_json::{self, Value as Json};
+
+use dsl::{Value, Format};
+
+/// The key which `serde_json` uses to pass numbers in textual form
+const JSON_NUMBER_TOKEN: &str = "$serde_json::private::Number";
+
+/// How values should be serialized
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueStyle {
+    /// Always as strings (default)
+    String,
+    /// As numbers when they keep the exact textual form, otherwise as strings
+    Number,
+}
+
+thread_local! {
+    // style of the innermost running `to_json` or `Format::serialize_styled`
+    static VALUE_STYLE: Cell<ValueStyle> = Cell::new(ValueStyle::String);
+}
+
+/// Run serialization with specified style of values, restoring previous style afterwards
+fn styled<R, F: FnOnce() -> R>(style: ValueStyle, func: F) -> R {
+    struct Restore(ValueStyle);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            VALUE_STYLE.with(|cell| cell.set(self.0));
+        }
+    }
+
+    let _restore = Restore(VALUE_STYLE.with(|cell| cell.replace(style)));
+    func()
+}
+
+/// Convert document to JSON tree serializing values in specified style
+pub fn to_json<T: Serialize>(value: &T, style: ValueStyle) -> Result<Json, String> {
+    styled(style, || serde_json::to_value(value)).map_err(|err| err.to_string())
+}
+
+impl Format {
+    /// Serialize value to document of this format with values in specified style
+    pub fn serialize_styled<T: Serialize>(self, value: &T, style: ValueStyle) -> Result<Vec<u8>, String> {
+        styled(style, || self.serialize(value))
+    }
+}
+
+/// Parse value from textual form
+pub fn parse_value(text: &str) -> Result<Value, String> {
+    match Value::from_str(text.trim()) {
+        Ok(value) if !value.is_nan() => Ok(value),
+        _ => Err(format!("Invalid decimal `{}`", text)),
+    }
+}
+
+pub fn serialize<S: Serializer>(value: &Value, serializer: S) -> Result<S::Ok, S::Error> {
+    serialize_as(value, VALUE_STYLE.with(|cell| cell.get()), serializer)
+}
+
+/// Serialize value in specified style
+///
+/// Numbers are used only when they print exactly as the value does, so `2.50` stays a string.
+pub fn serialize_as<S: Serializer>(value: &Value, style: ValueStyle, serializer: S) -> Result<S::Ok, S::Error> {
+    let text = value.to_string();
+
+    if style == ValueStyle::Number && value.is_finite() {
+        if let Ok(int) = i64::from_str(&text) {
+            if int.to_string() == text {
+                return serializer.serialize_i64(int);
+            }
+        }
+        if text.contains('.') {
+            if let Ok(float) = f64::from_str(&text) {
+                if float.to_string() == text {
+                    return serializer.serialize_f64(float);
+                }
+            }
+        }
+    }
+
+    serializer.serialize_str(&text)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Value, D::Error> {
+    deserializer.deserialize_any(ValueVisitor)
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a decimal number or string")
+    }
+
+    fn visit_str<E: de::Error>(self, text: &str) -> Result<Value, E> {
+        parse_value(text).map_err(E::custom)
+    }
+
+    fn visit_i64<E: de::Error>(self, int: i64) -> Result<Value, E> {
+        Ok(Value::from(int))
+    }
+
+    fn visit_u64<E: de::Error>(self, int: u64) -> Result<Value, E> {
+        Ok(Value::from(int))
+    }
+
+    fn visit_f64<E: de::Error>(self, float: f64) -> Result<Value, E> {
+        // shortest representation which reads back to the same float
+        parse_value(&float.to_string()).map_err(E::custom)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        match map.next_key::<String>()? {
+            Some(ref key) if key == JSON_NUMBER_TOKEN => {
+                let text: String = map.next_value()?;
+                parse_value(&text).map_err(de::Error::custom)
+            },
+            _ => Err(de::Error::invalid_type(de::Unexpected::Map, &self)),
+        }
+    }
+}
+
+/// Serialization of optional values
+///
+/// Use it on `Option<Value>` fields with `#[serde(with = "::dsl::value::opt")]`.
+pub mod opt {
+    use std::fmt;
+
+    use serde::{Serializer, Deserializer};
+    use serde::de::{self, Visitor};
+
+    use dsl::{Value};
+
+    pub fn serialize<S: Serializer>(value: &Option<Value>, serializer: S) -> Result<S::Ok, S::Error> {
+        match *value {
+            Some(ref value) => super::serialize(value, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Value>, D::Error> {
+        deserializer.deserialize_option(OptionVisitor)
+    }
+
+    struct OptionVisitor;
+
+    impl<'de> Visitor<'de> for OptionVisitor {
+        type Value = Option<Value>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("an optional decimal number or string")
+        }
+
+        fn visit_none<E: de::Error>(self) -> Result<Option<Value>, E> {
+            Ok(None)
+        }
+
+        fn visit_unit<E: de::Error>(self) -> Result<Option<Value>, E> {
+            Ok(None)
+        }
+
+        fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Option<Value>, D::Error> {
+            super::deserialize(deserializer).map(Some)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ValueStyle, to_json, parse_value};
+    use dsl::{Ctrl, InputControl};
+    use serde_json::{Value as Json, from_str, to_string};
+
+    #[test]
+    fn test_value_from_string() {
+        let ctrl: Ctrl = from_str(r#"{ "name": "a", "value": "2.50" }"#).unwrap();
+
+        assert_eq!(ctrl.value.to_string(), "2.50");
+        assert!(from_str::<Ctrl>(r#"{ "name": "a", "value": "two" }"#).is_err());
+    }
+
+    #[test]
+    fn test_value_from_number() {
+        let ctrl: InputControl = from_str(r#"{ "name": "a", "value": 2 }"#).unwrap();
+        assert_eq!(ctrl.value, 2.into());
+
+        let ctrl: InputControl = from_str(r#"{ "name": "a", "value": -1.5e3 }"#).unwrap();
+        assert_eq!(ctrl.value, parse_value("-1500").unwrap());
+
+        // more digits than f64 can hold
+        let ctrl: InputControl = from_str(r#"{ "name": "a", "value": 0.1000000000000000000000000001 }"#).unwrap();
+        assert_eq!(ctrl.value.to_string(), "0.1000000000000000000000000001");
+
+        let ctrl: InputControl = from_str(r#"{ "name": "a", "value": 2.50 }"#).unwrap();
+        assert_eq!(ctrl.value.to_string(), "2.50");
+    }
+
+    #[test]
+    fn test_value_style() {
+        let ctrl = InputControl::new("a", parse_value("2.5").unwrap());
+
+        assert_eq!(to_string(&ctrl).unwrap(), r#"{"name":"a","value":"2.5"}"#);
+        assert_eq!(to_json(&ctrl, ValueStyle::String).unwrap(), json!({ "name": "a", "value": "2.5" }));
+        assert_eq!(to_json(&ctrl, ValueStyle::Number).unwrap(), from_str::<Json>(r#"{"name":"a","value":2.5}"#).unwrap());
+
+        let ctrl = InputControl::new("a", 7);
+        assert_eq!(to_json(&ctrl, ValueStyle::Number).unwrap(), json!({ "name": "a", "value": 7 }));
+
+        // scale and exponent are kept
+        for text in &["2.50", "1E+3", "0.1000000000000000000000000001"] {
+            let ctrl = InputControl::new("a", parse_value(text).unwrap());
+            assert_eq!(to_json(&ctrl, ValueStyle::Number).unwrap(), json!({ "name": "a", "value": text }));
+        }
+
+        // style does not leak out of the call
+        assert_eq!(to_string(&ctrl).unwrap(), r#"{"name":"a","value":"2.5"}"#);
+    }
+}
@@ -3,10 +3,14 @@ extern crate decimal;
 #[macro_use]
 extern crate serde_derive;
 extern crate serde;
+#[macro_use]
 extern crate serde_json;
+extern crate serde_yaml;
+extern crate toml;
+extern crate rmp_serde;
+extern crate serde_cbor;
 
 extern crate futures;
-extern crate future_pubsub;
 extern crate tokio;
 extern crate hyper;
 
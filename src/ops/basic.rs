@@ -1,32 +1,61 @@
 use decimal::{d128};
 use futures::{Stream};
 
-use dsl::{NodeKind, InputKind, OutputKind, Observables, NodeDecl, NodeDecls};
+use dsl::{NodeKind, InputKind, OutputKind, Link, Value, Signal, Expr, Dual, Interval, Observables, NodeSite, NodeDecl, NodeDecls};
 
 fn neg_impl(mut ins: Observables) -> Observables {
     let (_, ai) = ins.get("a").into();
     
-    let ro = Box::new(ai.map(|a| -a));
+    let ro = Box::new(ai.map(|a| a.map(|a| -a)));
     
     Observables::new().put("=", ro)
 }
 
+fn neg_eval(ins: &[Value]) -> Vec<Value> {
+    vec![-ins[0]]
+}
+
+fn neg_symbolic(ins: &[Expr]) -> Vec<Expr> {
+    vec![Expr::neg(ins[0].clone())]
+}
+
+fn neg_deriv(ins: &[Dual]) -> Vec<Dual> {
+    vec![ins[0].neg()]
+}
+
+fn neg_interval(ins: &[Interval]) -> Result<Vec<Interval>, String> {
+    Ok(vec![ins[0].neg()])
+}
+
+fn neg_identity(site: &NodeSite) -> Option<(&'static str, Link, &'static str)> {
+    match site.source(site.input("a")?)? {
+        (src, "=") if src.kind == "-" => Some(("=", src.get_in("a")?.link.clone(), "double negation")),
+        _ => None,
+    }
+}
+
 fn neg_decl(decls: &mut NodeDecls) {
     decls.put(NodeDecl::new(
         NodeKind::new("-")
             .with_in(InputKind::new("a"))
-            .with_out(OutputKind::new("=")),
-        neg_impl));
+            .with_out(OutputKind::new("="))
+            .with_pure(),
+        neg_impl)
+        .with_eval(neg_eval)
+        .with_symbolic(neg_symbolic)
+        .with_deriv(neg_deriv)
+        .with_interval(neg_interval)
+        .with_identity(neg_identity));
 }
 
 fn add_impl(mut ins: Observables) -> Observables {
     let (av, ai) = ins.get("a").into();
     let (bv, bi) = ins.get("b").into();
     
-    let ro = Box::new(ai.map(|_| ()).select(bi.map(|_| ())).map(move |_| {
+    let ro = Box::new(ai.map(|a| a.cause).select(bi.map(|b| b.cause)).map(move |cause| {
         trace!("{:?} + {:?}", *av.borrow(), *bv.borrow());
         match (*av.borrow(), *bv.borrow()) {
-            (Some(a), Some(b)) => Some(a + b),
+            (Some(a), Some(b)) => Some(Signal::new(a + b).with_cause(cause)),
             _ => None,
         }
     }).skip_while(|opt| Ok(opt.is_none())).map(Option::unwrap));
@@ -34,39 +63,97 @@ fn add_impl(mut ins: Observables) -> Observables {
     Observables::new().put("=", ro)
 }
 
+fn add_eval(ins: &[Value]) -> Vec<Value> {
+    vec![ins[0] + ins[1]]
+}
+
+fn add_symbolic(ins: &[Expr]) -> Vec<Expr> {
+    vec![Expr::add(ins[0].clone(), ins[1].clone())]
+}
+
+fn add_deriv(ins: &[Dual]) -> Vec<Dual> {
+    vec![ins[0].add(&ins[1])]
+}
+
+fn add_interval(ins: &[Interval]) -> Result<Vec<Interval>, String> {
+    Ok(vec![ins[0].add(&ins[1])])
+}
+
+fn add_identity(site: &NodeSite) -> Option<(&'static str, Link, &'static str)> {
+    let (a, b) = (site.input("a")?, site.input("b")?);
+    if site.is_const(b, 0) { return Some(("=", a.clone(), "addition of zero")); }
+    if site.is_const(a, 0) { return Some(("=", b.clone(), "addition of zero")); }
+    None
+}
+
 fn add_decl(decls: &mut NodeDecls) {
     decls.put(NodeDecl::new(
         NodeKind::new("+")
             .with_in(InputKind::new("a"))
             .with_in(InputKind::new("b"))
-            .with_out(OutputKind::new("=")),
-        add_impl));
+            .with_out(OutputKind::new("="))
+            .with_pure(),
+        add_impl)
+        .with_eval(add_eval)
+        .with_symbolic(add_symbolic)
+        .with_deriv(add_deriv)
+        .with_interval(add_interval)
+        .with_identity(add_identity));
 }
 
 fn inv_impl(mut ins: Observables) -> Observables {
     let (_, ai) = ins.get("a").into();
     
-    let ro = Box::new(ai.map(|a| d128::from(1)/a));
+    let ro = Box::new(ai.map(|a| a.map(|a| d128::from(1)/a)));
     
     Observables::new().put("=", ro)
 }
 
+fn inv_eval(ins: &[Value]) -> Vec<Value> {
+    vec![d128::from(1) / ins[0]]
+}
+
+fn inv_symbolic(ins: &[Expr]) -> Vec<Expr> {
+    vec![Expr::inv(ins[0].clone())]
+}
+
+fn inv_deriv(ins: &[Dual]) -> Vec<Dual> {
+    vec![ins[0].inv()]
+}
+
+fn inv_interval(ins: &[Interval]) -> Result<Vec<Interval>, String> {
+    ins[0].inv().map(|a| vec![a])
+}
+
+fn inv_identity(site: &NodeSite) -> Option<(&'static str, Link, &'static str)> {
+    match site.source(site.input("a")?)? {
+        (src, "=") if src.kind == "^-1" => Some(("=", src.get_in("a")?.link.clone(), "double inversion")),
+        _ => None,
+    }
+}
+
 fn inv_decl(decls: &mut NodeDecls) {
     decls.put(NodeDecl::new(
         NodeKind::new("^-1")
             .with_in(InputKind::new("a"))
-            .with_out(OutputKind::new("=")),
-        inv_impl));
+            .with_out(OutputKind::new("="))
+            .with_pure(),
+        inv_impl)
+        .with_eval(inv_eval)
+        .with_symbolic(inv_symbolic)
+        .with_deriv(inv_deriv)
+        .with_interval(inv_interval)
+        .with_identity(inv_identity));
 }
 
 fn mul_impl(mut ins: Observables) -> Observables {
     let (av, ai) = ins.get("a").into();
     let (bv, bi) = ins.get("b").into();
     
-    let ro = Box::new(ai.map(|_| ()).select(bi.map(|_| ())).map(move |_| {
+    let ro = Box::new(ai.map(|a| a.cause).select(bi.map(|b| b.cause)).map(move |cause| {
         trace!("{:?} * {:?}", *av.borrow(), *bv.borrow());
         match (*av.borrow(), *bv.borrow()) {
-            (Some(a), Some(b)) => Some(a * b),
+            (Some(a), Some(b)) => Some(Signal::new(a * b).with_cause(cause)),
             _ => None,
         }
     }).skip_while(|opt| Ok(opt.is_none())).map(Option::unwrap));
@@ -74,13 +161,42 @@ fn mul_impl(mut ins: Observables) -> Observables {
     Observables::new().put("=", ro)
 }
 
+fn mul_eval(ins: &[Value]) -> Vec<Value> {
+    vec![ins[0] * ins[1]]
+}
+
+fn mul_symbolic(ins: &[Expr]) -> Vec<Expr> {
+    vec![Expr::mul(ins[0].clone(), ins[1].clone())]
+}
+
+fn mul_deriv(ins: &[Dual]) -> Vec<Dual> {
+    vec![ins[0].mul(&ins[1])]
+}
+
+fn mul_interval(ins: &[Interval]) -> Result<Vec<Interval>, String> {
+    Ok(vec![ins[0].mul(&ins[1])])
+}
+
+fn mul_identity(site: &NodeSite) -> Option<(&'static str, Link, &'static str)> {
+    let (a, b) = (site.input("a")?, site.input("b")?);
+    if site.is_const(b, 1) { return Some(("=", a.clone(), "multiplication by one")); }
+    if site.is_const(a, 1) { return Some(("=", b.clone(), "multiplication by one")); }
+    None
+}
+
 fn mul_decl(decls: &mut NodeDecls) {
     decls.put(NodeDecl::new(
         NodeKind::new("*")
             .with_in(InputKind::new("a"))
             .with_in(InputKind::new("b"))
-            .with_out(OutputKind::new("=")),
-        mul_impl));
+            .with_out(OutputKind::new("="))
+            .with_pure(),
+        mul_impl)
+        .with_eval(mul_eval)
+        .with_symbolic(mul_symbolic)
+        .with_deriv(mul_deriv)
+        .with_interval(mul_interval)
+        .with_identity(mul_identity));
 }
 
 pub fn basic_ops(decls: &mut NodeDecls) {